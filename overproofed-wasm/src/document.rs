@@ -0,0 +1,585 @@
+//! The recipe document for the web app to keep its state in, instead of a JSON tree of its own.
+//!
+//! Things in a document are addressed by mix, `undefined` for the dough, then by `Part`, with
+//! an ingredient id for `Part.Ingredient`. Edits throw if what they address isn't there.
+
+use js_sys::Array as JsArray;
+use wasm_bindgen::prelude::*;
+
+use super::{default, set, wrapped};
+use wrapped::document::{self, Document, MixIngredient};
+use wrapped::message;
+use wrapped::{Index, IngredientId, MixPath, Path, Slot, Value};
+
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Part {
+    Total,
+    Flour,
+    /// only has solved values, it's the total less the flour
+    NonFlour,
+    Ingredient,
+}
+
+/// The fields of a solved value, like `wrapped::Field`.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Field {
+    Weight,
+    Bakers,
+    WeightInMixes,
+    WeightLessMixes,
+    PercentInMixes,
+    PercentLessMixes,
+    PercentOfTotal,
+}
+
+impl From<Field> for wrapped::Field {
+    fn from(field: Field) -> Self {
+        match field {
+            Field::Weight => wrapped::Field::Weight,
+            Field::Bakers => wrapped::Field::Bakers,
+            Field::WeightInMixes => wrapped::Field::WeightInMixes,
+            Field::WeightLessMixes => wrapped::Field::WeightLessMixes,
+            Field::PercentInMixes => wrapped::Field::PercentInMixes,
+            Field::PercentLessMixes => wrapped::Field::PercentLessMixes,
+            Field::PercentOfTotal => wrapped::Field::PercentOfTotal,
+        }
+    }
+}
+
+/// What the baker entered for an item, a copy, see `RecipeDocument.set_inputs`.
+#[wasm_bindgen]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Inputs(document::Inputs);
+
+#[wasm_bindgen]
+impl Inputs {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        default()
+    }
+
+    pub fn weight(&self) -> Option<Value> {
+        self.0.weight
+    }
+
+    pub fn set_weight(&mut self, v: Option<Value>) {
+        self.0.weight = v;
+    }
+
+    pub fn bakers(&self) -> Option<Value> {
+        self.0.bakers
+    }
+
+    pub fn set_bakers(&mut self, v: Option<Value>) {
+        self.0.bakers = v;
+    }
+
+    /// in the dough, the fraction of the item's weight from mixes; in a mix, the fraction of
+    /// the item's weight in the dough that's in this mix
+    pub fn in_other(&self) -> Option<Value> {
+        self.0.in_other
+    }
+
+    pub fn set_in_other(&mut self, v: Option<Value>) {
+        self.0.in_other = v;
+    }
+
+    /// only for ingredients in the dough, the weight added to the final dough
+    pub fn final_weight(&self) -> Option<Value> {
+        self.0.final_weight
+    }
+
+    pub fn set_final_weight(&mut self, v: Option<Value>) {
+        self.0.final_weight = v;
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct RecipeDocument {
+    document: Document,
+    /// from the last `solve` or `apply`, until the next edit
+    solved: Option<Solution>,
+    /// the id of the last `solve_request`, until the next edit, `apply` only takes a response
+    /// to it
+    requested: Option<u32>,
+}
+
+/// the recipe to find a value's index in, and every value and the code of its state
+#[derive(Debug)]
+struct Solution {
+    recipe: wrapped::Recipe,
+    values: Vec<Value>,
+    states: Vec<u8>,
+}
+
+fn no_such(what: &'static str) -> wrapped::Error {
+    wrapped::Error::Shape(what)
+}
+
+impl RecipeDocument {
+    fn edit(&mut self) -> &mut Document {
+        self.solved = None;
+        self.requested = None;
+        &mut self.document
+    }
+
+    fn mix_mut(&mut self, i: u16) -> Result<&mut document::Mix, wrapped::Error> {
+        self.edit()
+            .mixes
+            .get_mut(i as usize)
+            .ok_or(no_such("no such mix"))
+    }
+
+    fn inputs_ref(
+        &self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+    ) -> Result<&document::Inputs, wrapped::Error> {
+        if part == Part::NonFlour {
+            return Err(wrapped::Error::Shape("nonflour has no inputs"));
+        }
+
+        let document = &self.document;
+
+        let inputs = match (mix, part) {
+            (None, Part::Total) => Some(&document.total),
+            (None, Part::Flour) => Some(&document.flour),
+            (None, _) => document.ingredient(id).map(|i| &i.inputs),
+            (Some(i), part) => {
+                let mix = document
+                    .mixes
+                    .get(i as usize)
+                    .ok_or(no_such("no such mix"))?;
+                match part {
+                    Part::Total => Some(&mix.total),
+                    Part::Flour => Some(&mix.flour),
+                    _ => mix.ingredient(id).map(|i| &i.inputs),
+                }
+            }
+        };
+
+        inputs.ok_or(no_such("no such ingredient"))
+    }
+
+    fn inputs_mut(
+        &mut self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+    ) -> Result<&mut document::Inputs, wrapped::Error> {
+        if part == Part::NonFlour {
+            return Err(wrapped::Error::Shape("nonflour has no inputs"));
+        }
+
+        let document = self.edit();
+
+        let inputs = match (mix, part) {
+            (None, Part::Total) => Some(&mut document.total),
+            (None, Part::Flour) => Some(&mut document.flour),
+            (None, _) => document.ingredient_mut(id).map(|i| &mut i.inputs),
+            (Some(i), part) => {
+                let mix = document
+                    .mixes
+                    .get_mut(i as usize)
+                    .ok_or(no_such("no such mix"))?;
+                match part {
+                    Part::Total => Some(&mut mix.total),
+                    Part::Flour => Some(&mut mix.flour),
+                    _ => mix.ingredient_mut(id).map(|i| &mut i.inputs),
+                }
+            }
+        };
+
+        inputs.ok_or(no_such("no such ingredient"))
+    }
+
+    fn index(
+        &self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+        field: Field,
+    ) -> Option<Index> {
+        let recipe = &self.solved.as_ref()?.recipe;
+
+        let mix = match mix {
+            None => MixPath::Dough,
+            Some(i) => MixPath::Mix(i as usize),
+        };
+
+        let slot = match part {
+            Part::Total => Slot::Total,
+            Part::Flour => Slot::Flour,
+            Part::NonFlour => Slot::NonFlour,
+            Part::Ingredient => self.document.slot(id)?,
+        };
+
+        recipe.index(Path::new(mix, slot, field.into()))
+    }
+}
+
+#[wasm_bindgen]
+impl RecipeDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// reads either kind of share link, see `wrapped::share`
+    pub fn from_link(s: &str) -> Result<RecipeDocument, JsError> {
+        let document = wrapped::share::decode(s, &default())?;
+        Ok(Self { document, ..default() })
+    }
+
+    pub fn to_link(&self) -> String {
+        wrapped::share::encode(&self.document)
+    }
+
+    pub fn title(&self) -> String {
+        self.document.meta.title.clone()
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.edit().meta.title = title;
+    }
+
+    pub fn notes(&self) -> String {
+        self.document.meta.notes.clone()
+    }
+
+    pub fn set_notes(&mut self, notes: String) {
+        self.edit().meta.notes = notes;
+    }
+
+    pub fn source(&self) -> Option<String> {
+        self.document.meta.source.clone()
+    }
+
+    pub fn set_source(&mut self, source: Option<String>) {
+        self.edit().meta.source = source;
+    }
+
+    pub fn yield_count(&self) -> Option<u32> {
+        self.document.meta.yields.as_ref().map(|y| y.count)
+    }
+
+    pub fn yield_unit(&self) -> Option<String> {
+        self.document.meta.yields.as_ref().map(|y| y.unit.clone())
+    }
+
+    /// like 4 "balls" or 2 "loaves", `undefined` for no yield
+    pub fn set_yield(&mut self, count: Option<u32>, unit: String) {
+        self.edit().meta.yields = count.map(|count| document::Yield { count, unit });
+    }
+
+    /// every ingredient in the dough, in order
+    pub fn ingredient_ids(&self) -> Vec<IngredientId> {
+        self.document.ingredients.iter().map(|i| i.id).collect()
+    }
+
+    /// adds to the end of the dough, returns the new ingredient's id
+    ///
+    /// throws if the biggest id is already `u32::MAX`
+    pub fn add_ingredient(
+        &mut self,
+        name: String,
+        is_flour: bool,
+    ) -> Result<IngredientId, JsError> {
+        Ok(self.edit().add_ingredient(name, is_flour)?)
+    }
+
+    /// from the dough and every mix
+    pub fn remove_ingredient(&mut self, id: IngredientId) -> Result<(), JsError> {
+        match self.edit().remove_ingredient(id) {
+            true => Ok(()),
+            false => Err(no_such("no such ingredient").into()),
+        }
+    }
+
+    /// moves the ingredient to `position` in the dough's list
+    pub fn move_ingredient(&mut self, id: IngredientId, position: usize) -> Result<(), JsError> {
+        let ingredients = &mut self.edit().ingredients;
+        let from = ingredients
+            .iter()
+            .position(|i| i.id == id)
+            .ok_or(no_such("no such ingredient"))?;
+        let ingredient = ingredients.remove(from);
+        ingredients.insert(position.min(ingredients.len()), ingredient);
+        Ok(())
+    }
+
+    pub fn ingredient_name(&self, id: IngredientId) -> Option<String> {
+        self.document.ingredient(id).map(|i| i.name.clone())
+    }
+
+    pub fn set_ingredient_name(&mut self, id: IngredientId, name: String) -> Result<(), JsError> {
+        let ingredient = self.edit().ingredient_mut(id);
+        ingredient.ok_or(no_such("no such ingredient"))?.name = name;
+        Ok(())
+    }
+
+    pub fn is_flour(&self, id: IngredientId) -> Option<bool> {
+        self.document.ingredient(id).map(|i| i.is_flour)
+    }
+
+    pub fn set_is_flour(&mut self, id: IngredientId, is_flour: bool) -> Result<(), JsError> {
+        let ingredient = self.edit().ingredient_mut(id);
+        ingredient.ok_or(no_such("no such ingredient"))?.is_flour = is_flour;
+        Ok(())
+    }
+
+    pub fn mix_count(&self) -> usize {
+        self.document.mixes.len()
+    }
+
+    /// adds an empty mix to the end, returns its position
+    pub fn add_mix(&mut self, name: String) -> Result<u16, JsError> {
+        let mixes = &mut self.edit().mixes;
+        let i = u16::try_from(mixes.len()).map_err(|_| wrapped::Error::Shape("too many mixes"))?;
+        mixes.push(document::Mix { name, ..default() });
+        Ok(i)
+    }
+
+    pub fn remove_mix(&mut self, i: u16) -> Result<(), JsError> {
+        self.mix_mut(i)?;
+        self.edit().mixes.remove(i as usize);
+        Ok(())
+    }
+
+    pub fn mix_name(&self, i: u16) -> Option<String> {
+        self.document
+            .mix_name(MixPath::Mix(i as usize))
+            .map(Into::into)
+    }
+
+    pub fn set_mix_name(&mut self, i: u16, name: String) -> Result<(), JsError> {
+        self.mix_mut(i)?.name = name;
+        Ok(())
+    }
+
+    /// the ingredients used in the mix, in order
+    pub fn mix_ingredient_ids(&self, i: u16) -> Option<Vec<IngredientId>> {
+        let mix = self.document.mixes.get(i as usize)?;
+        Some(mix.ingredients.iter().map(|i| i.id).collect())
+    }
+
+    /// uses an ingredient from the dough in the mix, does nothing if it already is
+    pub fn add_to_mix(&mut self, i: u16, id: IngredientId) -> Result<(), JsError> {
+        if self.document.ingredient(id).is_none() {
+            return Err(no_such("no such ingredient").into());
+        }
+
+        let mix = self.mix_mut(i)?;
+        if mix.ingredient(id).is_none() {
+            mix.ingredients
+                .push(MixIngredient { id, inputs: default() });
+        }
+        Ok(())
+    }
+
+    pub fn remove_from_mix(&mut self, i: u16, id: IngredientId) -> Result<(), JsError> {
+        let mix = self.mix_mut(i)?;
+        let position = mix
+            .ingredients
+            .iter()
+            .position(|i| i.id == id)
+            .ok_or(no_such("no such ingredient"))?;
+        mix.ingredients.remove(position);
+        Ok(())
+    }
+
+    pub fn inputs(
+        &self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+    ) -> Result<Inputs, JsError> {
+        Ok(Inputs(*self.inputs_ref(mix, part, id)?))
+    }
+
+    pub fn set_inputs(
+        &mut self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+        inputs: &Inputs,
+    ) -> Result<(), JsError> {
+        *self.inputs_mut(mix, part, id)? = inputs.0;
+        Ok(())
+    }
+
+    /// builds and solves, for `value` and `state`
+    pub fn solve(&mut self) -> Result<(), JsError> {
+        let (recipe, values) = self.document.solve()?;
+        let states = values.states().iter().map(|s| s.code()).collect();
+        let values = values.as_slice().to_vec();
+        self.solved = Some(Solution { recipe, values, states });
+        Ok(())
+    }
+
+    /// a request for `solve_message`, to solve somewhere else like a web worker
+    ///
+    /// `apply` only takes the response to the last request, so use a new `id` for each.
+    pub fn solve_request(&mut self, id: u32, steps: bool) -> Vec<u8> {
+        self.requested = Some(id);
+        let options = message::Options { steps };
+        message::Request { id, document: self.document.clone(), options }.encode()
+    }
+
+    /// uses a response to `solve_request` instead of solving, for `value` and `state`
+    ///
+    /// throws the response's error, or if it isn't the response to the last `solve_request`
+    /// since the document was edited; `SolveResponse.decode` already threw for unknown state
+    /// codes
+    pub fn apply(&mut self, response: &SolveResponse) -> Result<(), JsError> {
+        let message::Response { id, error, values, states, .. } = &response.0;
+
+        if self.requested != Some(*id) {
+            return Err(wrapped::Error::Shape("response isn't to the last request").into());
+        }
+
+        if let Some(error) = error {
+            return Err(JsError::new(error));
+        }
+
+        let (recipe, built) = self.document.build()?;
+        if values.len() != built.len() || states.len() != built.len() {
+            return Err(wrapped::Error::Shape("response isn't for this document").into());
+        }
+
+        self.solved = Some(Solution { recipe, values: values.clone(), states: states.clone() });
+        Ok(())
+    }
+
+    /// `undefined` if it wasn't solved since the last edit or isn't there, NaN if it's unsolved
+    pub fn value(
+        &self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+        field: Field,
+    ) -> Option<Value> {
+        let index = self.index(mix, part, id, field)?;
+        self.solved.as_ref()?.values.get(index as usize).copied()
+    }
+
+    /// the code of the value's state like in `Solved.states`, `undefined` like `value`
+    pub fn state(
+        &self,
+        mix: Option<u16>,
+        part: Part,
+        id: IngredientId,
+        field: Field,
+    ) -> Option<u8> {
+        let index = self.index(mix, part, id, field)?;
+        self.solved.as_ref()?.states.get(index as usize).copied()
+    }
+}
+
+/// Solves a request from `RecipeDocument.solve_request` and returns the response, for a web
+/// worker to call with what it's posted and post back what it gets.
+///
+/// Throws if the request can't be read, a document that can't be solved is an error in the
+/// response instead.
+#[wasm_bindgen]
+pub fn solve_message(request: &[u8]) -> Result<Vec<u8>, JsError> {
+    let request = message::Request::decode(request, &default())?;
+    Ok(message::solve(&request).encode())
+}
+
+/// A response from `solve_message`, see `wrapped::message::Response`.
+#[wasm_bindgen]
+pub struct SolveResponse(message::Response);
+
+#[wasm_bindgen]
+impl SolveResponse {
+    pub fn decode(bytes: &[u8]) -> Result<SolveResponse, JsError> {
+        Ok(Self(message::Response::decode(bytes)?))
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.0.error.clone()
+    }
+
+    /// every value by index, NaN if it's unsolved
+    pub fn values(&self) -> Vec<Value> {
+        self.0.values.clone()
+    }
+
+    /// the code of every value's state, like in `Solved.states`
+    pub fn states(&self) -> Vec<u8> {
+        self.0.states.clone()
+    }
+
+    /// objects with the `index` and `value` of each value in the order they were solved and the
+    /// `kind` of math that solved it, if the request asked for steps
+    pub fn steps(&self) -> JsArray {
+        self.0
+            .diagnostics
+            .steps
+            .iter()
+            .map(|step| {
+                let object = js_sys::Object::new();
+                set(&object, "index", step.index.into());
+                set(&object, "value", step.value.into());
+                set(&object, "kind", step.kind.into());
+                JsValue::from(object)
+            })
+            .collect()
+    }
+
+    pub fn unsolved(&self) -> Vec<Index> {
+        self.0.diagnostics.unsolved.clone()
+    }
+
+    pub fn undefined(&self) -> Vec<Index> {
+        self.0.diagnostics.undefined.clone()
+    }
+
+    pub fn infinite(&self) -> Vec<Index> {
+        self.0.diagnostics.infinite.clone()
+    }
+
+    /// the indexes in each math that the values don't agree with
+    pub fn conflicts(&self) -> JsArray {
+        self.0
+            .diagnostics
+            .conflicts
+            .iter()
+            .map(|indexes| JsValue::from(js_sys::Uint16Array::from(indexes.as_slice())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_link() {
+        /* a V1 link from before documents, it's just the web app's JSON */
+        let old = include_str!("../../overproofed/corpus/v1_pizza.link").trim();
+        let pizza =
+            wrapped::format::from_str(include_str!("../../overproofed/corpus/v1_pizza.json"));
+
+        let doc = RecipeDocument::from_link(old).unwrap_or_else(|_| panic!("old link"));
+        assert_eq!(Ok(doc.document.clone()), pizza);
+
+        /* and a V0 link to the same recipe, from before nulls */
+        let older = include_str!("../../overproofed/corpus/v0_pizza.link").trim();
+        let doc0 = RecipeDocument::from_link(older).unwrap_or_else(|_| panic!("older link"));
+        assert_eq!(doc0.document, doc.document);
+
+        let again =
+            RecipeDocument::from_link(&doc.to_link()).unwrap_or_else(|_| panic!("new link"));
+        assert_eq!(again.document, doc.document);
+    }
+}
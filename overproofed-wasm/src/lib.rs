@@ -41,218 +41,17 @@ pub fn set_console_panic_hook() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 }
 
-// mod de {
-//     use serde::{Deserialize, Serialize};
-//     use wasm_bindgen::JsValue;
-
-//     use super::{Amounts, Item, Mix, NullableAmounts, Recipe};
-//     use ::overproofed::{Grams, Percent};
-
-//     #[derive(Serialize, Deserialize)]
-//     #[serde(remote = "Recipe")]
-//     pub struct CompactRecipe(
-//         #[serde(getter = "Recipe::items", with = "CompactItemVec")] Vec<Item>,
-//         #[serde(getter = "Recipe::total", with = "CompactMix")] Mix,
-//         #[serde(getter = "Recipe::mixes", with = "CompactMixVec")] Vec<Mix>,
-//         #[serde(getter = "Recipe::finals")] Vec<Grams>,
-//     );
-
-//     #[derive(Serialize, Deserialize)]
-//     #[serde(remote = "Item")]
-//     pub struct CompactItem(
-//         #[serde(getter = "Item::name")] String,
-//         #[serde(getter = "Item::is_flour")] bool,
-//     );
-
-//     #[allow(non_snake_case)]
-//     mod CompactItemVec {
-//         use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
-
-//         use super::{CompactItem, Item};
-
-//         pub fn serialize<S>(vec: &Vec<Item>, er: S) -> Result<S::Ok, S::Error>
-//         where
-//             S: Serializer,
-//         {
-//             let mut seq = er.serialize_seq(Some(vec.len()))?;
-//             for element in vec {
-//                 seq.serialize_element(&_Item(element))?;
-//             }
-//             return seq.end();
-
-//             #[derive(Serialize)]
-//             struct _Item<'i>(#[serde(with = "CompactItem")] &'i Item);
-//         }
-
-//         pub fn deserialize<'de, D>(er: D) -> Result<Vec<Item>, D::Error>
-//         where
-//             D: Deserializer<'de>,
-//         {
-//             return Ok(Vec::<_Item>::deserialize(er)?
-//                 .into_iter()
-//                 .map(|_Item(i)| i)
-//                 .collect());
-
-//             #[derive(Deserialize)]
-//             struct _Item(#[serde(with = "CompactItem")] Item);
-//         }
-//     }
-
-//     #[derive(Deserialize, Serialize)]
-//     #[serde(remote = "Mix")]
-//     pub struct CompactMix(
-//         #[serde(getter = "Mix::name")] String,
-//         #[serde(getter = "Mix::amounts", with = "CompactOptionAmountsVec")] Vec<JsValue>,
-//         #[serde(getter = "Mix::flour", with = "CompactAmounts")] Amounts,
-//         #[serde(getter = "Mix::total", with = "CompactAmounts")] Amounts,
-//     );
-
-//     #[allow(non_snake_case)]
-//     mod CompactMixVec {
-//         use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
-
-//         use super::{CompactMix, Mix};
-
-//         pub fn serialize<S>(vec: &Vec<Mix>, er: S) -> Result<S::Ok, S::Error>
-//         where
-//             S: Serializer,
-//         {
-//             let mut seq = er.serialize_seq(Some(vec.len()))?;
-//             for element in vec {
-//                 seq.serialize_element(&_Mix(element))?;
-//             }
-//             return seq.end();
-
-//             #[derive(serde::Serialize)]
-//             struct _Mix<'i>(#[serde(with = "CompactMix")] &'i Mix);
-//         }
-
-//         pub fn deserialize<'de, D>(er: D) -> Result<Vec<Mix>, D::Error>
-//         where
-//             D: Deserializer<'de>,
-//         {
-//             return Ok(Vec::<_Mix>::deserialize(er)?
-//                 .into_iter()
-//                 .map(|_Mix(m)| m)
-//                 .collect());
-
-//             #[derive(Deserialize)]
-//             struct _Mix(#[serde(with = "CompactMix")] Mix);
-//         }
-//     }
-
-//     /* shitty code generation + shitty wasm_bindgen bindings = nightmare */
-//     #[allow(non_snake_case)]
-//     mod CompactOptionAmountsVec {
-//         use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
-//         use wasm_bindgen::JsValue;
-
-//         use super::{Amounts, CompactAmounts, Mix};
-//         use crate::NullableAmounts;
-
-//         pub fn serialize<S>(
-//             vec: &Vec<JsValue /* Option<Amounts> */>,
-//             er: S,
-//         ) -> Result<S::Ok, S::Error>
-//         where
-//             S: Serializer,
-//         {
-//             let mut seq = er.serialize_seq(Some(vec.len()))?;
-//             for element in vec {
-//                 seq.serialize_element(
-//                     &NullableAmounts::from(element.clone())
-//                         .into_option()
-//                         .as_ref()
-//                         .map(_Amounts),
-//                 )?;
-//             }
-//             return seq.end();
-
-//             #[derive(Serialize)]
-//             struct _Amounts<'i>(#[serde(with = "CompactAmounts")] &'i Amounts);
-//         }
-
-//         pub fn deserialize<'de, D>(er: D) -> Result<Vec<JsValue /* Option<Amounts> */>, D::Error>
-//         where
-//             D: Deserializer<'de>,
-//         {
-//             return Ok(Vec::<Option<_Amounts>>::deserialize(er)?
-//                 .into_iter()
-//                 .map(|option| option.map(|_Amounts(a)| a))
-//                 .map(NullableAmounts::from)
-//                 .map(NullableAmounts::into_jsvalue)
-//                 .collect());
-
-//             #[derive(Deserialize)]
-//             struct _Amounts(#[serde(with = "CompactAmounts")] Amounts);
-//         }
-//     }
-
-//     #[derive(Deserialize, Serialize)]
-//     #[serde(remote = "Amounts")]
-//     pub struct CompactAmounts(
-//         #[serde(getter = "Amounts::bakers")] Percent,
-//         #[serde(getter = "Amounts::weight")] Grams,
-//         #[serde(getter = "Amounts::in_other")] Percent,
-//     );
-
-//     impl From<CompactRecipe> for Recipe {
-//         fn from(c: CompactRecipe) -> Self {
-//             let CompactRecipe(items, total, mixes, finals) = c;
-//             let mut recipe = Self::default();
-//             recipe.set_items(items);
-//             recipe.set_total(total.into());
-//             recipe.set_mixes(mixes);
-//             recipe.set_finals(finals);
-//             recipe
-//         }
-//     }
-
-//     impl From<CompactItem> for Item {
-//         fn from(c: CompactItem) -> Self {
-//             let CompactItem(name, is_flour) = c;
-//             let mut item = Self::default();
-//             item.set_name(name);
-//             item.set_is_flour(is_flour);
-//             item
-//         }
-//     }
-
-//     impl From<CompactMix> for Mix {
-//         fn from(c: CompactMix) -> Self {
-//             let CompactMix(name, amounts, flour, total) = c;
-//             let mut mix = Self::default();
-//             mix.set_name(name);
-//             mix.set_amounts(amounts);
-//             mix.set_flour(flour);
-//             mix.set_total(total);
-//             mix
-//         }
-//     }
-
-//     impl From<CompactAmounts> for Amounts {
-//         fn from(c: CompactAmounts) -> Self {
-//             let CompactAmounts(bakers, weight, in_other) = c;
-//             let mut amounts = Self::default();
-//             amounts.set_bakers(bakers);
-//             amounts.set_weight(weight);
-//             amounts.set_in_other(in_other);
-//             amounts
-//         }
-//     }
-// }
+pub mod document;
 
+/// throws if the link is bad or over `share::Limits`
 #[wasm_bindgen]
-pub fn base64_expand(s: &str) -> Option<String> {
-    let smol = base64::decode_config(&s, base64::URL_SAFE_NO_PAD).ok()?;
-    let big = lz4_flex::decompress_size_prepended(&smol).ok()?;
-    String::from_utf8(big).ok()
+pub fn base64_expand(s: &str) -> Result<String, JsError> {
+    Ok(wrapped::share::expand(s, &default())?)
 }
 
 #[wasm_bindgen]
 pub fn compact_base64(s: &str) -> String {
-    let smol = lz4_flex::block::compress_prepend_size(s.as_bytes());
-    base64::encode_config(&smol, base64::URL_SAFE_NO_PAD)
+    wrapped::share::compact(s)
 }
 
 pub fn default<T: Default>() -> T {
@@ -267,9 +66,13 @@ pub struct RecipeWriter {
     values: wrapped::Values,
     recipe: wrapped::Recipe,
     cursor: (MixCursor, ItemCursor),
+    /// the id the next item in each mix gets, the dough first then each mix, holes use up an
+    /// id too so that ids match the position of the item in the web app's list
+    next_ids: Vec<wrapped::IngredientId>,
 }
 
 #[wasm_bindgen]
+#[derive(Default)]
 pub struct NewItemFlags(u8);
 
 #[wasm_bindgen]
@@ -320,6 +123,83 @@ pub mod new_item_flags {
     pub const MIX_ITEM: u8       /*_*/ = 0b100;
 }
 
+/// The flat recipe `RecipeWriter.load` takes.
+///
+/// Items are in the order of the web app's list and an item's id is its position in it. Each
+/// item has a byte of flags, only `new_item_flags::NONFLOUR` matters. Membership is a bit per
+/// item for the dough then for each mix, from the lowest bit of each byte, set if the item is in
+/// it; item `i` of mix `m` is bit `m * items + i` with the dough as mix 0.
+///
+/// The inputs, NaN for what isn't an input, are `FIELDS` values for each of the dough's total,
+/// its flour, its nonflour, then each item in the dough, followed by the same for each mix.
+pub mod layout {
+    /// weight, bakers, the fraction in mixes or of the total, and the weight less mixes
+    pub const FIELDS: usize = 4;
+    /// in the layout `load` returns, for a field the item doesn't have like the weight less
+    /// mixes of an item in a mix
+    pub const NO_INDEX: i32 = -1;
+}
+
+/// The inputs to vary for `RecipeWriter.sweep`, in order; the last axis varies fastest.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Sweep(Vec<wrapped::sweep::Axis>);
+
+#[wasm_bindgen]
+impl Sweep {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self(default())
+    }
+
+    pub fn axis(&mut self, index: Index, values: &[Value]) {
+        self.0
+            .push(wrapped::sweep::Axis::new(index, values.to_vec()))
+    }
+
+    /// from `start` to `stop` inclusive, every `step`
+    ///
+    /// throws if `step` isn't positive, if anything isn't finite, or if there are too many steps
+    pub fn range(
+        &mut self,
+        index: Index,
+        start: Value,
+        stop: Value,
+        step: Value,
+    ) -> Result<(), JsError> {
+        let axis = wrapped::sweep::Axis::range(index, start, stop, step)?;
+        self.0.push(axis);
+        Ok(())
+    }
+
+    /// throws if there are more than a sweep allows
+    pub fn variants(&self) -> Result<usize, JsError> {
+        Ok(wrapped::sweep::variants(&self.0)?)
+    }
+}
+
+/// What `RecipeWriter.solve` found.
+#[wasm_bindgen]
+pub struct Solved {
+    values: JsArray,
+    states: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Solved {
+    /// sparse, only has the values that were solved
+    pub fn values(&self) -> JsArray {
+        self.values.clone()
+    }
+
+    /// a code for every value, see `wrapped::State::code`
+    ///
+    /// 0 unsolved, 1 input, 2 derived, 3 default, 4 undefined, 5 conflicting, 6 free, 7 infinite
+    pub fn states(&self) -> Vec<u8> {
+        self.states.clone()
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 enum MixCursor {
     Dough,
@@ -331,26 +211,100 @@ enum ItemCursor {
     Total,
     Flour,
     NonFlour,
-    Flours(u16),
-    NonFlours(u16),
+    Flours(wrapped::IngredientId),
+    NonFlours(wrapped::IngredientId),
 }
 
 impl RecipeWriter {
     /// returns the Item from the RecipeWriter's Recipe pointed to by the RecipeWriter's cursor
-    fn cursor_item(&mut self) -> Option<&mut wrapped::Item> {
-        let (mix, item) = self.cursor;
+    fn cursor_item(&mut self) -> Result<&mut wrapped::Item, wrapped::Error> {
+        let (_, item) = self.cursor;
 
-        let mix = match mix {
-            MixCursor::Dough => &mut self.recipe.dough,
-            MixCursor::Mix(i) => self.recipe.mixes.get_mut(i as usize)?,
-        };
+        let mix = self.cursor_mix()?;
 
         match item {
             ItemCursor::Total => Some(&mut mix.total),
             ItemCursor::Flour => Some(&mut mix.flour),
             ItemCursor::NonFlour => Some(&mut mix.nonflour),
-            ItemCursor::Flours(i) => mix.flours.get_mut(i as usize)?.as_mut(),
-            ItemCursor::NonFlours(i) => mix.nonflours.get_mut(i as usize)?.as_mut(),
+            ItemCursor::Flours(id) => find_mut(&mut mix.flours, id),
+            ItemCursor::NonFlours(id) => find_mut(&mut mix.nonflours, id),
+        }
+        .ok_or(wrapped::Error::InvalidCursor)
+    }
+
+    /// returns the Mix from the RecipeWriter's Recipe pointed to by the RecipeWriter's cursor
+    fn cursor_mix(&mut self) -> Result<&mut wrapped::Mix, wrapped::Error> {
+        match self.cursor {
+            (MixCursor::Dough, _) => Ok(&mut self.recipe.dough),
+            (MixCursor::Mix(i), _) => self
+                .recipe
+                .mixes
+                .get_mut(i as usize)
+                .ok_or(wrapped::Error::InvalidCursor),
+        }
+    }
+
+    /// the id counter for the mix the cursor points at
+    fn cursor_next_id(&mut self) -> Result<&mut wrapped::IngredientId, wrapped::Error> {
+        let i = match self.cursor {
+            (MixCursor::Dough, _) => 0,
+            (MixCursor::Mix(i), _) => i as usize + 1,
+        };
+        self.next_ids
+            .get_mut(i)
+            .ok_or(wrapped::Error::InvalidCursor)
+    }
+
+    /// solves, calling `f` with each value as it's solved and the index of the math that did
+    ///
+    /// values solved by an earlier solve are solved again, only inputs are kept
+    fn solve_with(
+        &mut self,
+        mut f: impl FnMut(Index, Value, usize),
+    ) -> Result<wrapped::solve::Solver, wrapped::Error> {
+        let Self { recipe, values, .. } = self;
+
+        values.reset();
+
+        let mut solver = wrapped::solve::Solver::validated(recipe, values)?;
+
+        while let Some((index, value, math)) = solver.step(values)? {
+            f(index, value, math);
+        }
+
+        Ok(solver)
+    }
+
+    /// sets the fields of `object` for a math in `solver`, see `explain`
+    fn explain_math(
+        &self,
+        object: &js_sys::Object,
+        solver: &wrapped::solve::Solver,
+        paths: &wrapped::Paths,
+        math: usize,
+    ) {
+        let Some(math) = solver.math(math) else {
+            return;
+        };
+
+        let indexes = math.indexes().map(JsValue::from).collect::<JsArray>();
+        let path_strings = math
+            .indexes()
+            .map(|i| JsValue::from(paths.display(i).to_string()))
+            .collect::<JsArray>();
+
+        set(object, "kind", math.kind().into());
+        set(object, "indexes", indexes.into());
+        set(object, "paths", path_strings.into());
+
+        #[cfg(debug_assertions)]
+        {
+            set(object, "line", math.line().into());
+            set(
+                object,
+                "text",
+                math.display(&self.values, paths).to_string().into(),
+            );
         }
     }
 
@@ -364,110 +318,402 @@ impl RecipeWriter {
     }
 }
 
+/// the indexes of `layout::FIELDS` for an item
+fn fields(item: &wrapped::Item) -> [Option<Index>; layout::FIELDS] {
+    let (in_other, less_mixes) = match item {
+        wrapped::Item::WithMixes(item) => (
+            item.percent_in_mixes.into(),
+            Some(item.weight_less_mixes.into()),
+        ),
+        wrapped::Item::InMix(item) => (item.percent_of_total.into(), None),
+    };
+    [
+        Some(item.weight.into()),
+        Some(item.bakers.into()),
+        Some(in_other),
+        less_mixes,
+    ]
+}
+
+fn set(object: &js_sys::Object, key: &str, value: JsValue) {
+    js_sys::Reflect::set(object, &key.into(), &value).expect("setting on a plain object");
+}
+
+fn find_mut(
+    ingredients: &mut [wrapped::Ingredient],
+    id: wrapped::IngredientId,
+) -> Option<&mut wrapped::Item> {
+    ingredients
+        .iter_mut()
+        .find(|ingredient| ingredient.id == id)
+        .map(|ingredient| &mut ingredient.item)
+}
+
+impl Default for RecipeWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl RecipeWriter {
     #[wasm_bindgen(constructor)]
-    pub fn new(capacity: usize) -> Self {
-        let mut values = wrapped::Values::from(Vec::with_capacity(capacity));
-        let recipe = values.minimal_recipe();
+    pub fn new() -> Self {
+        let mut values = wrapped::Values::new();
+        let recipe = values
+            .minimal_recipe()
+            .expect("an empty recipe fits in MAX_VALUES");
         let cursor = (MixCursor::Dough, ItemCursor::Total);
-        Self { values, recipe, cursor }
+        let next_ids = vec![0];
+        Self { values, recipe, cursor, next_ids }
     }
 
-    pub fn set(&mut self, i: Index, v: Value) {
-        *self.values.value_mut(i) = v;
+    pub fn set(&mut self, i: Index, v: Value) -> Result<(), JsError> {
+        *self.values.value_mut(i)? = v;
+        Ok(())
     }
 
-    pub fn solve(&mut self) -> Option<JsArray> {
-        let Self { recipe, values, .. } = self;
+    /// `undefined` if `i` isn't a value, NaN if it's unsolved
+    pub fn get(&self, i: Index) -> Option<Value> {
+        self.values.value_opt(i)
+    }
 
-        if values.did_overflow() {
-            butt!("Values.did_overflow() {:?}", values.how_overflow());
-            return None;
-        }
+    /// the code of the value's state, like in `Solved.states`
+    pub fn state(&self, i: Index) -> u8 {
+        self.values.state(i).code()
+    }
 
+    /// makes the value unsolved so that it's solved for instead of being an input
+    ///
+    /// throws like `set` if `i` isn't a value
+    pub fn clear(&mut self, i: Index) -> Result<(), JsError> {
+        Ok(self.values.clear(i)?)
+    }
+
+    /// sparse, see `Solved`; `solve_into` or `values` are faster for big recipes
+    pub fn solve(&mut self) -> Result<Solved, JsError> {
         let results = JsArray::new();
-        let mut solver = wrapped::solve::Solver::new(recipe, values);
+        self.solve_with(|index, value, _| {
+            results.set(index as u32, value.into());
+        })?;
+
+        let states = self
+            .values
+            .states()
+            .iter()
+            .map(|state| state.code())
+            .collect();
 
-        // butt!("{:#?}", &self.recipe);
+        Ok(Solved { values: results, states })
+    }
 
-        while let Some((index, value, _math)) = solver.step(values) {
-            // let math = solver.math(_math).unwrap();
-            // let line = math.line();
-            // let math_display = math.display(&values);
-            // butt!("{index:>3}\tL{line:>3}\t{math_display}");
+    /// solves and copies every value, and the code of its state, into the arrays
+    ///
+    /// throws if either is shorter than `value_count`
+    pub fn solve_into(&mut self, values: &mut [Value], states: &mut [u8]) -> Result<(), JsError> {
+        self.solve_with(|_, _, _| ())?;
 
-            // if value.is_infinite() {
-            //     let math = solver.math(_math).unwrap();
-            //     let line = math.line();
-            //     let math_display = math.display(&values);
-            //     butt!("UH OH {index:>3}\tL{line:>3}\t{math_display}");
-            // }
+        let len = self.values.len();
+        if values.len() < len || states.len() < len {
+            return Err(wrapped::Error::Shape("arrays shorter than the values").into());
+        }
 
-            results.set(index as u32, value.into());
+        values[..len].copy_from_slice(self.values.as_slice());
+        for (code, state) in states.iter_mut().zip(self.values.states()) {
+            *code = state.code();
+        }
+
+        Ok(())
+    }
+
+    /// solves without copying anything out, for use with `values` and `states`
+    pub fn solve_in_place(&mut self) -> Result<(), JsError> {
+        self.solve_with(|_, _, _| ())?;
+        Ok(())
+    }
+
+    pub fn value_count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// a copy of every value by index, NaN if it's unsolved
+    pub fn values(&self) -> js_sys::Float32Array {
+        js_sys::Float32Array::from(self.values.as_slice())
+    }
+
+    /// a copy of the code of every value's state, like in `Solved.states`
+    pub fn states(&self) -> js_sys::Uint8Array {
+        let codes = self.values.states().iter().map(|state| state.code());
+        js_sys::Uint8Array::from(codes.collect::<Vec<_>>().as_slice())
+    }
+
+    /// Replaces the recipe with one from a flat layout, see `layout`, in one call instead of
+    /// a few for every item.
+    ///
+    /// Returns the index of each value in `inputs`, in the same order, or `layout::NO_INDEX`.
+    /// Throws if the lengths of `membership` and `inputs` don't match `mixes` and `flags`.
+    pub fn load(
+        &mut self,
+        mixes: u16,
+        flags: &[u8],
+        membership: &[u8],
+        inputs: &[Value],
+    ) -> Result<Vec<i32>, JsError> {
+        use new_item_flags::*;
+
+        let items = flags.len();
+        if membership.len() * 8 < (mixes as usize + 1) * items {
+            return Err(wrapped::Error::Shape("membership shorter than the items").into());
         }
 
-        // if solver.unsolved_value_to_math_index_pairs().len() > 0 {
-        //     butt!("unsolved...");
-        //     for &(index, _math) in solver.unsolved_value_to_math_index_pairs().iter() {
-        //         let math = solver.math(_math).unwrap();
-        //         let line = math.line();
-        //         let math_display = math.display(&values);
-        //         butt!("{index:>3}\tL{line:>3}\t{math_display}");
-        //     }
-        // }
+        let member = |m: usize, i: usize| {
+            let bit = m * items + i;
+            membership[bit / 8] >> (bit % 8) & 1 == 1
+        };
+
+        let mut values = wrapped::Values::new();
+        let mut recipe = values.minimal_recipe()?;
+        for _ in 0..mixes {
+            recipe.mixes.push(values.minimal_mix()?);
+        }
+
+        let mut inputs = inputs.iter();
+        let mut indexes = Vec::with_capacity(inputs.len());
+
+        for m in 0..=mixes as usize {
+            let mix = match m {
+                0 => &mut recipe.dough,
+                m => &mut recipe.mixes[m - 1],
+            };
+
+            let mut slots = vec![
+                fields(&mix.total),
+                fields(&mix.flour),
+                fields(&mix.nonflour),
+            ];
+
+            for (i, &flag) in flags.iter().enumerate().filter(|&(i, _)| member(m, i)) {
+                let item: wrapped::Item = match m {
+                    0 => values.new_item()?.into(),
+                    _ => values.new_mix_item()?.into(),
+                };
+                slots.push(fields(&item));
+
+                let list = match flag & IS_FLOUR_MASK {
+                    NONFLOUR => &mut mix.nonflours,
+                    _ => &mut mix.flours,
+                };
+                list.push(wrapped::Ingredient::new(i as wrapped::IngredientId, item));
+            }
 
-        Some(results)
+            for index in slots.into_iter().flatten() {
+                let input = *inputs
+                    .next()
+                    .ok_or(wrapped::Error::Shape("fewer inputs than the layout"))?;
+
+                match index {
+                    Some(index) => {
+                        if !input.is_nan() {
+                            *values.value_mut(index)? = input;
+                        }
+                        indexes.push(index as i32);
+                    }
+                    None if input.is_nan() => indexes.push(layout::NO_INDEX),
+                    None => {
+                        return Err(wrapped::Error::Shape("input for a value not there").into())
+                    }
+                }
+            }
+        }
+
+        if inputs.next().is_some() {
+            return Err(wrapped::Error::Shape("more inputs than the layout").into());
+        }
+
+        let next_id = wrapped::IngredientId::try_from(items)
+            .map_err(|_| wrapped::Error::Shape("too many items"))?;
+
+        self.values = values;
+        self.recipe = recipe;
+        self.next_ids = vec![next_id; mixes as usize + 1];
+        self.dough();
+
+        Ok(indexes)
+    }
+
+    /// Solves like `solve` and returns how, for explaining values to the baker.
+    ///
+    /// An object with `steps`, each value in the order it was solved as `index` and `value`,
+    /// `unsolved`, the values that weren't and each math they're in as `index`, `infinite`, the
+    /// values that a math found to be infinite as `index`, and `conflicts`, the maths that the
+    /// values don't agree with. Each also has the math's `kind`, like `percent_of`, the `indexes`
+    /// in it and their `paths`, and in debug builds the `line` of the rule and the math as
+    /// `text`.
+    pub fn explain(&mut self) -> Result<JsValue, JsError> {
+        let mut steps = Vec::new();
+        let solver = self.solve_with(|index, value, math| steps.push((index, value, math)))?;
+        let paths = wrapped::Paths::new(&self.recipe);
+
+        let js_steps = JsArray::new();
+        for (index, value, math) in steps {
+            let object = js_sys::Object::new();
+            set(&object, "index", index.into());
+            set(&object, "value", value.into());
+            self.explain_math(&object, &solver, &paths, math);
+            js_steps.push(&object);
+        }
+
+        let unsolved = JsArray::new();
+        for &(index, math) in solver.unsolved_value_to_math_index_pairs() {
+            let object = js_sys::Object::new();
+            set(&object, "index", index.into());
+            self.explain_math(&object, &solver, &paths, math);
+            unsolved.push(&object);
+        }
+
+        let infinite = JsArray::new();
+        for &(index, math) in solver.infinite() {
+            let object = js_sys::Object::new();
+            set(&object, "index", index.into());
+            self.explain_math(&object, &solver, &paths, math);
+            infinite.push(&object);
+        }
+
+        let conflicts = JsArray::new();
+        for &math in solver.conflicts() {
+            let object = js_sys::Object::new();
+            self.explain_math(&object, &solver, &paths, math);
+            conflicts.push(&object);
+        }
+
+        let explanation = js_sys::Object::new();
+        set(&explanation, "steps", js_steps.into());
+        set(&explanation, "unsolved", unsolved.into());
+        set(&explanation, "infinite", infinite.into());
+        set(&explanation, "conflicts", conflicts.into());
+        Ok(explanation.into())
+    }
+
+    /// like `dough.flours[1].bakers`, for debugging
+    pub fn path(&self, index: Index) -> Option<String> {
+        self.recipe.path(index).map(|path| path.to_string())
+    }
+
+    /// Solves for every variant in `sweep`, see `Sweep`.
+    ///
+    /// This doesn't solve or modify the recipe, only the inputs are used.
+    ///
+    /// Returns a flat array with one row per variant of the axis values followed by the values
+    /// at `outputs`.
+    pub fn sweep(&self, sweep: &Sweep, outputs: &[Index]) -> Result<Vec<Value>, JsError> {
+        Ok(wrapped::sweep::sweep(&self.recipe, &self.values, &sweep.0, outputs)?.cells)
     }
 
     pub fn dough(&mut self) {
         self.map_cursor(|(_, _)| (MixCursor::Dough, ItemCursor::Total))
     }
 
-    /// fails silently if there already `u16::MAX` mixes.
-    pub fn new_mix(&mut self) {
-        let Some(i) = u16::try_from(self.recipe.mixes.len()).ok() else {
-            return;
-        };
+    /// throws if there are already `u16::MAX` mixes or there's no room for the mix's values
+    pub fn new_mix(&mut self) -> Result<(), JsError> {
+        let i = u16::try_from(self.recipe.mixes.len())
+            .map_err(|_| wrapped::Error::Shape("too many mixes"))?;
 
-        self.recipe.mixes.push(self.values.minimal_mix());
-        self.map_cursor(|_| (MixCursor::Mix(i), ItemCursor::Total))
+        self.recipe.mixes.push(self.values.minimal_mix()?);
+        self.next_ids.push(0);
+        self.map_cursor(|_| (MixCursor::Mix(i), ItemCursor::Total));
+        Ok(())
     }
 
-    /// fails silently if there are already `u16::MAX` items.
-    pub fn new_item(&mut self, NewItemFlags(flags): NewItemFlags) {
+    /// throws if the mix has run out of item ids or there's no room for the item's values
+    pub fn new_item(&mut self, NewItemFlags(flags): NewItemFlags) -> Result<(), JsError> {
         use new_item_flags::*;
 
-        let (mix, _) = self.cursor;
+        let next_id = self.cursor_next_id()?;
+        let id = *next_id;
+        *next_id = id
+            .checked_add(1)
+            .ok_or(wrapped::Error::Shape("too many items"))?;
 
-        let mix = match mix {
-            MixCursor::Dough => &mut self.recipe.dough,
-            MixCursor::Mix(i) => match self.recipe.mixes.get_mut(i as usize) {
-                None => return,
-                Some(mix) => mix,
-            },
+        let item: Option<wrapped::Item> = match flags & ITEM_KIND_MASK {
+            TOTAL_ITEM => Some(self.values.new_item()?.into()),
+            MIX_ITEM => Some(self.values.new_mix_item()?.into()),
+            _ => None,
         };
 
+        let mix = self.cursor_mix()?;
+
         let list = match flags & IS_FLOUR_MASK {
             NONFLOUR => &mut mix.nonflours,
             _ => &mut mix.flours,
         };
 
-        let Some(i) = u16::try_from(list.len()).ok() else {
-            return;
+        /* a hole only uses up an id */
+        if let Some(item) = item {
+            list.push(wrapped::Ingredient::new(id, item));
+        }
+
+        let newcursor = match flags & IS_FLOUR_MASK {
+            NONFLOUR => ItemCursor::NonFlours(id),
+            _ => ItemCursor::Flours(id),
         };
+        self.map_item_cursor(|_| newcursor);
+        Ok(())
+    }
 
-        list.push(match flags & ITEM_KIND_MASK {
-            TOTAL_ITEM => Some(self.values.new_item().into()),
-            MIX_ITEM => Some(self.values.new_mix_item().into()),
-            _ => None,
-        });
+    /// moves the cursor to the total of a mix from `new_mix`, in the order they were added
+    pub fn mix(&mut self, i: u16) -> Result<(), JsError> {
+        if i as usize >= self.recipe.mixes.len() {
+            return Err(wrapped::Error::InvalidCursor.into());
+        }
+        self.map_cursor(|_| (MixCursor::Mix(i), ItemCursor::Total));
+        Ok(())
+    }
 
-        let newcursor = match flags & IS_FLOUR_MASK {
-            NONFLOUR => ItemCursor::NonFlours(i),
-            _ => ItemCursor::Flours(i),
+    pub fn mix_count(&self) -> usize {
+        self.recipe.mixes.len()
+    }
+
+    /// moves the cursor to the flour or nonflour in the mix with the id from `new_item`
+    pub fn item(&mut self, id: wrapped::IngredientId) -> Result<(), JsError> {
+        let mix = self.cursor_mix()?;
+        let has = |list: &[wrapped::Ingredient]| list.iter().any(|i| i.id == id);
+
+        let newcursor = if has(&mix.flours) {
+            ItemCursor::Flours(id)
+        } else if has(&mix.nonflours) {
+            ItemCursor::NonFlours(id)
+        } else {
+            return Err(wrapped::Error::InvalidCursor.into());
         };
-        self.map_item_cursor(|_| newcursor)
+
+        self.map_item_cursor(|_| newcursor);
+        Ok(())
+    }
+
+    /// moves the cursor to the flour at `position` in the mix, holes don't count
+    pub fn flour_at(&mut self, position: usize) -> Result<(), JsError> {
+        let ingredient = self.cursor_mix()?.flours.get(position);
+        let id = ingredient.ok_or(wrapped::Error::InvalidCursor)?.id;
+        self.map_item_cursor(|_| ItemCursor::Flours(id));
+        Ok(())
+    }
+
+    /// moves the cursor to the nonflour at `position` in the mix, holes don't count
+    pub fn nonflour_at(&mut self, position: usize) -> Result<(), JsError> {
+        let ingredient = self.cursor_mix()?.nonflours.get(position);
+        let id = ingredient.ok_or(wrapped::Error::InvalidCursor)?.id;
+        self.map_item_cursor(|_| ItemCursor::NonFlours(id));
+        Ok(())
+    }
+
+    /// ids of the flours in the mix, in order
+    pub fn flour_ids(&mut self) -> Result<Vec<wrapped::IngredientId>, JsError> {
+        Ok(self.cursor_mix()?.flours.iter().map(|i| i.id).collect())
+    }
+
+    /// ids of the nonflours in the mix, in order
+    pub fn nonflour_ids(&mut self) -> Result<Vec<wrapped::IngredientId>, JsError> {
+        Ok(self.cursor_mix()?.nonflours.iter().map(|i| i.id).collect())
     }
 
     pub fn total(&mut self) {
@@ -482,50 +728,51 @@ impl RecipeWriter {
         self.map_item_cursor(|_| ItemCursor::NonFlour)
     }
 
-    pub fn weight(&mut self) -> Index {
-        self.cursor_item()
-            .map(|item| item.weight)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn weight(&mut self) -> Result<Index, JsError> {
+        Ok(self.cursor_item()?.weight.into())
     }
 
-    pub fn bakers(&mut self) -> Index {
-        self.cursor_item()
-            .map(|item| item.bakers)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn bakers(&mut self) -> Result<Index, JsError> {
+        Ok(self.cursor_item()?.bakers.into())
     }
 
-    pub fn weight_in_mixes(&mut self) -> Index {
-        self.cursor_item()
-            .and_then(|item| item.with_mixes())
-            .map(|item| item.weight_in_mixes)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn weight_in_mixes(&mut self) -> Result<Index, JsError> {
+        let item = self.cursor_item()?.with_mixes();
+        Ok(item
+            .ok_or(wrapped::Error::InvalidCursor)?
+            .weight_in_mixes
+            .into())
     }
 
-    pub fn weight_less_mixes(&mut self) -> Index {
-        self.cursor_item()
-            .and_then(|item| item.with_mixes())
-            .map(|item| item.weight_less_mixes)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn weight_less_mixes(&mut self) -> Result<Index, JsError> {
+        let item = self.cursor_item()?.with_mixes();
+        Ok(item
+            .ok_or(wrapped::Error::InvalidCursor)?
+            .weight_less_mixes
+            .into())
     }
 
-    pub fn percent_in_mixes(&mut self) -> Index {
-        self.cursor_item()
-            .and_then(|item| item.with_mixes())
-            .map(|item| item.percent_in_mixes)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn percent_in_mixes(&mut self) -> Result<Index, JsError> {
+        let item = self.cursor_item()?.with_mixes();
+        Ok(item
+            .ok_or(wrapped::Error::InvalidCursor)?
+            .percent_in_mixes
+            .into())
     }
 
-    pub fn percent_less_mixes(&mut self) -> Index {
-        self.cursor_item()
-            .and_then(|item| item.with_mixes())
-            .map(|item| item.percent_less_mixes)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn percent_less_mixes(&mut self) -> Result<Index, JsError> {
+        let item = self.cursor_item()?.with_mixes();
+        Ok(item
+            .ok_or(wrapped::Error::InvalidCursor)?
+            .percent_less_mixes
+            .into())
     }
 
-    pub fn percent_of_total(&mut self) -> Index {
-        self.cursor_item()
-            .and_then(|item| item.in_mix())
-            .map(|item| item.percent_of_total)
-            .unwrap_or(wrapped::OVERFLOW_INDEX)
+    pub fn percent_of_total(&mut self) -> Result<Index, JsError> {
+        let item = self.cursor_item()?.in_mix();
+        Ok(item
+            .ok_or(wrapped::Error::InvalidCursor)?
+            .percent_of_total
+            .into())
     }
 }
@@ -0,0 +1,434 @@
+//! A recipe as the baker writes it, with names, inputs and a bit about the recipe.
+//!
+//! The solver only sees anonymous indexes in a `Recipe`; this is what gets saved and shared and
+//! what a `Recipe` and its `Values` are built from. Only inputs are stored, everything else is
+//! solved for. `Paths` relates each value index back to a place in here, the ingredient in a
+//! `Slot` has the same id as the `Ingredient`.
+
+use super::path::{MixPath, Slot};
+use super::rules::{self, IngredientId, Recipe};
+use super::solve::Solver;
+use super::{Error, Index, Result, Value, Values};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Document {
+    pub meta: Meta,
+    /// every ingredient is in the dough, in this order
+    pub ingredients: Vec<Ingredient>,
+    /// the dough's total
+    pub total: Inputs,
+    /// the dough's total flour
+    pub flour: Inputs,
+    pub mixes: Vec<Mix>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Meta {
+    pub title: String,
+    pub yields: Option<Yield>,
+    pub notes: String,
+    /// where the recipe is from, like a book or a link
+    pub source: Option<String>,
+}
+
+/// Like 4 "balls" or 2 "loaves".
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Yield {
+    pub count: u32,
+    pub unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Ingredient {
+    pub id: IngredientId,
+    pub name: String,
+    pub is_flour: bool,
+    /// in the dough
+    pub inputs: Inputs,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Mix {
+    pub name: String,
+    pub total: Inputs,
+    pub flour: Inputs,
+    /// only the ingredients used in the mix, in the order they're listed
+    pub ingredients: Vec<MixIngredient>,
+}
+
+/// An ingredient from `Document::ingredients` used in a mix.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct MixIngredient {
+    pub id: IngredientId,
+    pub inputs: Inputs,
+}
+
+/// What the baker entered for an item, `None` for what should be solved.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Inputs {
+    pub weight: Option<Value>,
+    pub bakers: Option<Value>,
+    /// in the dough, the fraction of the item's weight from mixes; in a mix, the fraction of the
+    /// item's weight in the dough that's in this mix
+    pub in_other: Option<Value>,
+    /// only for ingredients in the dough, the weight added to the final dough
+    pub final_weight: Option<Value>,
+}
+
+impl Inputs {
+    pub fn weight(weight: Value) -> Self {
+        Inputs { weight: Some(weight), ..Default::default() }
+    }
+
+    pub fn bakers(bakers: Value) -> Self {
+        Inputs { bakers: Some(bakers), ..Default::default() }
+    }
+
+    pub fn in_other(in_other: Value) -> Self {
+        Inputs { in_other: Some(in_other), ..Default::default() }
+    }
+}
+
+impl Ingredient {
+    pub fn new(id: IngredientId, name: impl Into<String>, is_flour: bool) -> Self {
+        Ingredient { id, name: name.into(), is_flour, inputs: Default::default() }
+    }
+}
+
+impl Mix {
+    pub fn ingredient(&self, id: IngredientId) -> Option<&MixIngredient> {
+        self.ingredients
+            .iter()
+            .find(|ingredient| ingredient.id == id)
+    }
+
+    pub fn ingredient_mut(&mut self, id: IngredientId) -> Option<&mut MixIngredient> {
+        self.ingredients
+            .iter_mut()
+            .find(|ingredient| ingredient.id == id)
+    }
+}
+
+impl Document {
+    pub fn ingredient(&self, id: IngredientId) -> Option<&Ingredient> {
+        self.ingredients
+            .iter()
+            .find(|ingredient| ingredient.id == id)
+    }
+
+    pub fn ingredient_mut(&mut self, id: IngredientId) -> Option<&mut Ingredient> {
+        self.ingredients
+            .iter_mut()
+            .find(|ingredient| ingredient.id == id)
+    }
+
+    /// where the ingredient is in a mix of the built recipe, by whether it's a flour
+    pub fn slot(&self, id: IngredientId) -> Option<Slot> {
+        self.ingredient(id)
+            .map(|ingredient| match ingredient.is_flour {
+                true => Slot::Flours(id),
+                false => Slot::NonFlours(id),
+            })
+    }
+
+    /// adds an ingredient to the end of the dough with an unused id, returns the id
+    pub fn add_ingredient(
+        &mut self,
+        name: impl Into<String>,
+        is_flour: bool,
+    ) -> Result<IngredientId> {
+        let id = self.next_id()?;
+        self.ingredients.push(Ingredient::new(id, name, is_flour));
+        Ok(id)
+    }
+
+    /// removes the ingredient from the dough and every mix, false if there wasn't one
+    pub fn remove_ingredient(&mut self, id: IngredientId) -> bool {
+        let len = self.ingredients.len();
+        self.ingredients.retain(|ingredient| ingredient.id != id);
+        for mix in &mut self.mixes {
+            mix.ingredients.retain(|ingredient| ingredient.id != id);
+        }
+        self.ingredients.len() != len
+    }
+
+    /// an unused id for a new ingredient, one more than the biggest, fails with `Error::Shape` if
+    /// the biggest is `IngredientId::MAX`
+    pub fn next_id(&self) -> Result<IngredientId> {
+        self.ingredients
+            .iter()
+            .try_fold(0, |next: IngredientId, ingredient| {
+                ingredient.id.checked_add(1).map(|id| next.max(id))
+            })
+            .ok_or(Error::Shape("no ingredient id after the biggest one"))
+    }
+
+    /// the recipe and its values with the inputs set, fails with `Error::Invalid` if the
+    /// document uses ids it doesn't have or has the same id twice
+    pub fn build(&self) -> Result<(Recipe, Values)> {
+        self.build_in(Values::new())
+    }
+
+    /// like `build`, but adds to `values`, like one from `Values::with_limit`
+    pub fn build_in(&self, mut values: Values) -> Result<(Recipe, Values)> {
+        let mut recipe = values.minimal_recipe()?;
+
+        write(&mut values, &recipe.dough.total, &self.total)?;
+        write(&mut values, &recipe.dough.flour, &self.flour)?;
+
+        for ingredient in &self.ingredients {
+            let item = values.new_item()?.into();
+            write(&mut values, &item, &ingredient.inputs)?;
+            let list = match ingredient.is_flour {
+                true => &mut recipe.dough.flours,
+                false => &mut recipe.dough.nonflours,
+            };
+            list.push(rules::Ingredient::new(ingredient.id, item));
+        }
+
+        for mix in &self.mixes {
+            let mut built = values.minimal_mix()?;
+
+            write(&mut values, &built.total, &mix.total)?;
+            write(&mut values, &built.flour, &mix.flour)?;
+
+            for MixIngredient { id, inputs } in &mix.ingredients {
+                let item = values.new_mix_item()?.into();
+                write(&mut values, &item, inputs)?;
+                /* an id not in the dough goes in the flours to be found by `validate` */
+                let list = match self.ingredient(*id).is_none_or(|i| i.is_flour) {
+                    true => &mut built.flours,
+                    false => &mut built.nonflours,
+                };
+                list.push(rules::Ingredient::new(*id, item));
+            }
+
+            recipe.mixes.push(built);
+        }
+
+        recipe.validate()?;
+
+        Ok((recipe, values))
+    }
+
+    /// builds and solves, for when only the solved values are wanted
+    pub fn solve(&self) -> Result<(Recipe, Values)> {
+        let (recipe, mut values) = self.build()?;
+        Solver::validated(&recipe, &values)?.solve(&mut values)?;
+        Ok((recipe, values))
+    }
+
+    pub fn mix_name(&self, mix: MixPath) -> Option<&str> {
+        match mix {
+            MixPath::Dough => Some(&self.meta.title),
+            MixPath::Mix(i) => self.mixes.get(i).map(|mix| mix.name.as_str()),
+        }
+    }
+}
+
+fn write(values: &mut Values, item: &rules::Item, inputs: &Inputs) -> Result<()> {
+    let Inputs { weight, bakers, in_other, final_weight } = *inputs;
+
+    let (in_other_index, final_index): (Option<Index>, Option<Index>) = match item {
+        rules::Item::WithMixes(item) => (
+            Some(item.percent_in_mixes.into()),
+            Some(item.weight_less_mixes.into()),
+        ),
+        rules::Item::InMix(item) => (Some(item.percent_of_total.into()), None),
+    };
+
+    let fields = [
+        (Some(item.weight.into()), weight),
+        (Some(item.bakers.into()), bakers),
+        (in_other_index, in_other),
+        (final_index, final_weight),
+    ];
+
+    for (index, input) in fields {
+        match (index, input) {
+            (Some(index), Some(input)) => *values.value_mut(index)? = input,
+            (None, Some(_)) => return Err(Error::Shape("final weight of an item in a mix")),
+            (_, None) => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Field, Path, Paths, Problem, ProblemKind, Slot};
+
+    fn sourdough() -> Document {
+        let (wheat, rye, water, salt) = (0, 1, 2, 3);
+
+        Document {
+            meta: Meta {
+                title: "sourdough".into(),
+                yields: Some(Yield { count: 2, unit: "loaves".into() }),
+                ..Default::default()
+            },
+            ingredients: vec![
+                Ingredient {
+                    inputs: Inputs::bakers(0.8),
+                    ..Ingredient::new(wheat, "wheat", true)
+                },
+                Ingredient::new(rye, "rye", true),
+                Ingredient {
+                    inputs: Inputs::bakers(0.7),
+                    ..Ingredient::new(water, "water", false)
+                },
+                Ingredient {
+                    inputs: Inputs::bakers(0.02),
+                    ..Ingredient::new(salt, "salt", false)
+                },
+            ],
+            flour: Inputs::weight(500.0),
+            mixes: vec![Mix {
+                name: "levain".into(),
+                ingredients: vec![
+                    MixIngredient { id: rye, inputs: Inputs::in_other(1.0) },
+                    MixIngredient { id: water, inputs: Inputs::bakers(1.0) },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let document = sourdough();
+        let (recipe, values) = document.solve().unwrap();
+        let paths = Paths::new(&recipe);
+
+        let value = |mix, slot, field| {
+            let index = recipe.index(Path::new(mix, slot, field)).unwrap();
+            values.value(index).round()
+        };
+
+        assert_eq!(value(MixPath::Dough, Slot::Total, Field::Weight), 860.0);
+        assert_eq!(value(MixPath::Dough, Slot::Flours(1), Field::Weight), 100.0);
+        assert_eq!(
+            value(MixPath::Dough, Slot::NonFlours(2), Field::Weight),
+            350.0
+        );
+        assert_eq!(
+            value(MixPath::Dough, Slot::NonFlours(2), Field::WeightLessMixes),
+            250.0
+        );
+        assert_eq!(value(MixPath::Mix(0), Slot::Total, Field::Weight), 200.0);
+
+        /* only the document's inputs are inputs */
+        let inputs: Vec<_> = values
+            .inputs()
+            .map(|i| paths.display(i).to_string())
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                "dough.flour.weight",
+                "dough.flours[0].bakers",
+                "dough.nonflours[2].bakers",
+                "dough.nonflours[3].bakers",
+                "mixes[0].flours[1].percent_of_total",
+                "mixes[0].nonflours[2].bakers",
+            ]
+        );
+        assert!(values.states().iter().all(|s| s.is_solved()));
+
+        assert_eq!(document.mix_name(MixPath::Mix(0)), Some("levain"));
+        assert_eq!(document.next_id(), Ok(4));
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut document = sourdough();
+
+        let spelt = document.add_ingredient("spelt", true).unwrap();
+        assert_eq!(spelt, 4);
+        document.ingredient_mut(spelt).unwrap().inputs = Inputs::bakers(0.1);
+        document.ingredient_mut(0).unwrap().inputs = Inputs::bakers(0.7);
+        document.mixes[0].ingredient_mut(2).unwrap().inputs.bakers = Some(0.8);
+
+        let (recipe, values) = document.solve().unwrap();
+        let index = |slot| {
+            let path = Path::new(MixPath::Dough, slot, Field::Weight);
+            recipe.index(path).unwrap()
+        };
+        assert_eq!(document.slot(spelt), Some(Slot::Flours(spelt)));
+        assert_eq!(values.value(index(Slot::Flours(spelt))).round(), 50.0);
+
+        /* and it's gone from the levain too */
+        assert!(document.remove_ingredient(1));
+        assert!(!document.remove_ingredient(1));
+        assert_eq!(document.slot(1), None);
+        assert!(document.mixes[0].ingredient(1).is_none());
+        assert!(document.build().is_ok());
+    }
+
+    #[test]
+    fn test_bad_ids() {
+        let mut document = sourdough();
+        document.mixes[0]
+            .ingredients
+            .push(MixIngredient { id: 9, inputs: Inputs::default() });
+
+        let problem = Problem {
+            mix: MixPath::Mix(0),
+            slot: Slot::Flours(9),
+            kind: ProblemKind::NoMatchingTotal,
+        };
+        assert_eq!(document.build().err(), Some(Error::Invalid(vec![problem])));
+
+        let mut document = sourdough();
+        document.ingredients[1].id = 0;
+        assert!(matches!(document.build(), Err(Error::Invalid(_))));
+
+        let document = sourdough();
+        assert!(matches!(
+            document.build_in(Values::with_limit(10)),
+            Err(Error::Capacity { limit: 10 })
+        ));
+
+        let mut document = sourdough();
+        document.ingredients[3].id = IngredientId::MAX;
+        assert!(matches!(
+            document.add_ingredient("spelt", true),
+            Err(Error::Shape(_))
+        ));
+        assert_eq!(document.ingredients.len(), 4);
+    }
+
+    #[test]
+    fn test_final_weight_in_mix() {
+        let mut document = sourdough();
+        document.mixes[0].ingredients[0].inputs.final_weight = Some(10.0);
+        assert_eq!(
+            document.build().err(),
+            Some(Error::Shape("final weight of an item in a mix"))
+        );
+
+        let mut document = sourdough();
+        document.mixes[0].total.final_weight = Some(10.0);
+        assert!(matches!(document.build(), Err(Error::Shape(_))));
+    }
+}
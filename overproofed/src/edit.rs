@@ -0,0 +1,168 @@
+//! Deciding what to hold and what to give up when the user edits an input, see `Values::pin`.
+
+use super::rules::Recipe;
+use super::solve::Solver;
+use super::{Index, Result, State, Value, Values};
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Edit {
+    /// the user inputs after the edit, the edited input first
+    pub inputs: Vec<Index>,
+    /// pinned values that used to be derived and are now inputs, also in `inputs`
+    pub held: Vec<Index>,
+    /// inputs that conflicted with the edit or with a pin and are no longer inputs
+    pub released: Vec<Index>,
+}
+
+/// Sets `edited` to `value` and solves `values` again, returning the new set of inputs.
+///
+/// `values` should be solved for the current `inputs` since the values of pinned values are
+/// taken from it. Candidates are considered in order: the edited input, then pinned values, then
+/// the other inputs. Each is kept if it isn't determined by the ones kept before it, or if it is
+/// but agrees with them. Otherwise, an input is released and a derived pinned value is just not
+/// held.
+pub fn edit(
+    recipe: &Recipe,
+    values: &mut Values,
+    inputs: &[Index],
+    edited: impl Into<Index>,
+    value: Value,
+) -> Result<Edit> {
+    let edited = edited.into();
+    let pinned = values
+        .pinned()
+        .iter()
+        .cloned()
+        .filter(|&i| i != edited)
+        .filter(|&i| values.state(i).is_solved())
+        .map(|i| (i, values.value(i), inputs.contains(&i)));
+
+    let others = inputs
+        .iter()
+        .cloned()
+        .filter(|&i| i != edited && !values.is_pinned(i))
+        .map(|i| (i, values.value(i), true));
+
+    let candidates = pinned.chain(others).collect::<Vec<_>>();
+
+    let mut kept = vec![(edited, value)];
+    let mut edit = Edit { inputs: vec![edited], ..Default::default() };
+
+    for (index, value, is_input) in candidates {
+        let determined = solve_with(recipe, values, &kept)?;
+
+        if !determined.state(index).is_solved()
+            || (is_input && close(determined.value(index), value))
+        {
+            kept.push((index, value));
+            edit.inputs.push(index);
+            if !is_input {
+                edit.held.push(index);
+            }
+        } else if is_input {
+            edit.released.push(index);
+        }
+    }
+
+    *values = solve_with(recipe, values, &kept)?;
+
+    Ok(edit)
+}
+
+/// returns `values` with only `inputs` set, solved
+fn solve_with(recipe: &Recipe, values: &Values, inputs: &[(Index, Value)]) -> Result<Values> {
+    let mut solved = values.clone();
+    for i in 0..solved.len() as Index {
+        if solved.state(i) != State::Free {
+            solved.clear(i)?;
+        }
+    }
+
+    for &(index, value) in inputs {
+        *solved.value_mut(index)? = value;
+    }
+
+    Solver::new(recipe, &solved).solve(&mut solved)?;
+
+    Ok(solved)
+}
+
+fn close(a: Value, b: Value) -> bool {
+    (a - b).abs() <= 1e-5 * a.abs().max(b.abs()).max(f32::MIN_POSITIVE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WithMixes;
+
+    /// 1000g of dough at 70% hydration
+    fn hydrated() -> (Recipe, Values, WithMixes, WithMixes, Vec<Index>) {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        let inputs = vec![recipe.dough.total.weight.index(), water.bakers.index()];
+        *values.value_mut(recipe.dough.total.weight).unwrap() = 1000.0;
+        *values.value_mut(water.bakers).unwrap() = 0.70;
+
+        Solver::new(&recipe, &values).solve(&mut values).unwrap();
+
+        (recipe, values, wheat, water, inputs)
+    }
+
+    #[test]
+    fn test_edit_pinned_total() {
+        let (recipe, mut values, wheat, water, inputs) = hydrated();
+        let total = recipe.dough.total.weight;
+
+        values.pin(total).unwrap();
+
+        let edit = edit(&recipe, &mut values, &inputs, water.bakers, 0.80).unwrap();
+
+        assert_eq!(
+            edit,
+            Edit { inputs: vec![water.bakers.index(), total.index()], ..Default::default() }
+        );
+        assert_eq!(values.value(total), 1000.0);
+        assert_eq!(values.value(wheat.weight).round(), 556.0);
+        assert_eq!(values.value(water.weight).round(), 444.0);
+    }
+
+    #[test]
+    fn test_edit_pinned_flour() {
+        let (recipe, mut values, wheat, water, inputs) = hydrated();
+        let total = recipe.dough.total.weight;
+
+        values.pin(wheat.weight).unwrap();
+
+        let edit = edit(&recipe, &mut values, &inputs, water.bakers, 0.80).unwrap();
+
+        assert_eq!(
+            edit,
+            Edit {
+                inputs: vec![water.bakers.index(), wheat.weight.index()],
+                held: vec![wheat.weight.index()],
+                released: vec![total.index()],
+            }
+        );
+        assert_eq!(values.value(wheat.weight).round(), 588.0);
+        assert_eq!(values.value(water.weight).round(), 471.0);
+        assert_eq!(values.value(total).round(), 1059.0);
+    }
+
+    #[test]
+    fn test_edit_unpinned() {
+        let (recipe, mut values, wheat, water, inputs) = hydrated();
+        let total = recipe.dough.total.weight;
+
+        let edit = edit(&recipe, &mut values, &inputs, total, 1700.0).unwrap();
+
+        assert_eq!(
+            edit,
+            Edit { inputs: vec![total.index(), water.bakers.index()], ..Default::default() }
+        );
+        assert_eq!(values.value(wheat.weight).round(), 1000.0);
+    }
+}
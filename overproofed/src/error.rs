@@ -0,0 +1,63 @@
+use super::validate::Problem;
+use super::Index;
+
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// no room for more values, see `Values::with_limit`, or more than a sweep allows, see
+    /// `sweep::MAX_VARIANTS`
+    Capacity { limit: usize },
+    /// an index that isn't in the `Values` it was used with
+    InvalidIndex(Index),
+    /// an index of a value that was freed, see `Values::free`
+    Stale(Index),
+    /// the cursor of a writer doesn't point at something that has what was asked for, like the
+    /// weight of a hole
+    InvalidCursor,
+    /// a recipe that can't be built, like one with more mixes than fit in an `Index`
+    Shape(&'static str),
+    /// a recipe with structural problems, see `Recipe::validate`
+    Invalid(Vec<Problem>),
+    /// an argument that's out of range, like a target weight that isn't positive
+    Argument(&'static str),
+    /// a document that can't be read
+    #[cfg(feature = "json")]
+    Format(super::format::FormatError),
+    /// a share link that can't be read
+    #[cfg(feature = "share")]
+    Share(super::share::ShareError),
+    /// the solver's bookkeeping disagrees with itself, this is a bug
+    Inconsistent(&'static str),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Capacity { limit } => write!(f, "needs more than the limit of {limit}"),
+            Error::InvalidIndex(index) => write!(f, "no value at index {index}"),
+            #[cfg(feature = "json")]
+            Error::Format(e) => write!(f, "can't read recipe: {e}"),
+            #[cfg(feature = "share")]
+            Error::Share(e) => write!(f, "can't read share link: {e}"),
+            Error::Stale(index) => write!(f, "value at index {index} was freed"),
+            Error::InvalidCursor => write!(f, "cursor doesn't point at that"),
+            Error::Shape(why) => write!(f, "bad recipe shape: {why}"),
+            Error::Invalid(problems) => {
+                write!(f, "invalid recipe")?;
+                for (i, problem) in problems.iter().enumerate() {
+                    let sep = if i == 0 { ": " } else { ", " };
+                    write!(f, "{sep}{problem}")?;
+                }
+                Ok(())
+            }
+            Error::Argument(why) => write!(f, "invalid argument: {why}"),
+            Error::Inconsistent(why) => write!(f, "solver inconsistency: {why}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
@@ -0,0 +1,391 @@
+//! Reading and writing documents as JSON, including the versions the web app used to write.
+//!
+//! Every version is an object with its version number in `v`, except the first which has no
+//! `v` at all. Reading migrates whatever version it finds, one version at a time, to the current
+//! one; writing only ever writes the current one.
+//!
+//! - V0, the web app's first format, uses 0 for an amount that isn't an input.
+//! - V1, the same as V0 but with `null` for those instead.
+//! - V2, a `Document`, ingredients have ids and mixes only list what they use.
+
+use super::document::{Document, Ingredient, Inputs, Meta, Mix, MixIngredient};
+use super::{Error, Result, Value};
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+
+/// the version `to_string` writes
+pub const VERSION: u64 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// not JSON, or not the shape of its version, with serde's message
+    Malformed(String),
+    /// newer than `VERSION` maybe
+    UnknownVersion(u64),
+    /// lists that should line up don't, like a mix with amounts for fewer items than there are
+    Mismatch(&'static str),
+    /// a number that's infinite or NaN
+    NotFinite,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Malformed(why) => write!(f, "malformed: {why}"),
+            FormatError::UnknownVersion(v) => write!(f, "unknown version {v}"),
+            FormatError::Mismatch(why) => write!(f, "mismatched lists: {why}"),
+            FormatError::NotFinite => write!(f, "number isn't finite"),
+        }
+    }
+}
+
+impl From<FormatError> for Error {
+    fn from(e: FormatError) -> Self {
+        Error::Format(e)
+    }
+}
+
+fn malformed(e: serde_json::Error) -> Error {
+    FormatError::Malformed(e.to_string()).into()
+}
+
+/// reads any version, failing if it's malformed or the `Document` it migrates to doesn't build
+pub fn from_str(s: &str) -> Result<Document> {
+    let document = parse(s)?;
+    check(&document)?;
+    Ok(document)
+}
+
+/// reads and migrates to the current version without checking the document
+pub(crate) fn parse(s: &str) -> Result<Document> {
+    let mut object: Map<String, Json> = serde_json::from_str(s).map_err(malformed)?;
+
+    let document = match object.remove("v") {
+        None => v1::to_v2(v0::to_v1(from_object(object)?))?,
+        Some(v) => match v.as_u64() {
+            Some(1) => v1::to_v2(from_object(object)?)?,
+            Some(2) => from_object(object)?,
+            Some(v) => return Err(FormatError::UnknownVersion(v).into()),
+            None => return Err(FormatError::Malformed("v isn't a version".into()).into()),
+        },
+    };
+
+    Ok(document)
+}
+
+/// the numbers are finite and the document builds
+pub(crate) fn check(document: &Document) -> Result<()> {
+    check_finite(document)?;
+    document.build()?;
+    Ok(())
+}
+
+/// writes the current version
+pub fn to_string(document: &Document) -> String {
+    #[derive(Serialize)]
+    struct Versioned<'a> {
+        v: u64,
+        #[serde(flatten)]
+        document: &'a Document,
+    }
+
+    serde_json::to_string(&Versioned { v: VERSION, document })
+        .expect("a document only has things JSON can write")
+}
+
+fn from_object<T: for<'de> Deserialize<'de>>(object: Map<String, Json>) -> Result<T> {
+    serde_json::from_value(Json::Object(object)).map_err(malformed)
+}
+
+pub(crate) fn check_finite(document: &Document) -> Result<()> {
+    let Document { total, flour, ingredients, mixes, .. } = document;
+
+    let inputs = [total, flour]
+        .into_iter()
+        .chain(ingredients.iter().map(|ingredient| &ingredient.inputs))
+        .chain(mixes.iter().flat_map(|mix| {
+            [&mix.total, &mix.flour]
+                .into_iter()
+                .chain(mix.ingredients.iter().map(|ingredient| &ingredient.inputs))
+        }));
+
+    for &Inputs { weight, bakers, in_other, final_weight } in inputs {
+        let finite = [weight, bakers, in_other, final_weight]
+            .into_iter()
+            .flatten()
+            .all(Value::is_finite);
+        if !finite {
+            return Err(FormatError::NotFinite.into());
+        }
+    }
+
+    Ok(())
+}
+
+/* the web app's types, `Recipe.tsx` has the same by the same names */
+
+mod v1 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Recipe {
+        pub items: Vec<Item>,
+        pub total: Mix,
+        pub mixes: Vec<Mix>,
+        #[serde(rename = "final")]
+        pub finals: Vec<Option<Value>>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Item {
+        pub name: String,
+        pub is_flour: bool,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Mix {
+        pub name: String,
+        /// by position in `Recipe::items`, `None` for items not in the mix
+        pub amounts: Vec<Option<Amounts>>,
+        pub flour: Amounts,
+        pub total: Amounts,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Amounts {
+        pub weight: Option<Value>,
+        pub bakers: Option<Value>,
+        pub in_other: Option<Value>,
+    }
+
+    impl From<Amounts> for Inputs {
+        fn from(Amounts { weight, bakers, in_other }: Amounts) -> Self {
+            Inputs { weight, bakers, in_other, final_weight: None }
+        }
+    }
+
+    /// ids are positions in `items`
+    pub fn to_v2(recipe: Recipe) -> Result<Document> {
+        let Recipe { items, total, mixes, finals } = recipe;
+
+        if total.amounts.len() != items.len() {
+            return Err(FormatError::Mismatch("total amounts and items").into());
+        }
+
+        if finals.len() != items.len() {
+            return Err(FormatError::Mismatch("final weights and items").into());
+        }
+
+        if mixes.iter().any(|mix| mix.amounts.len() != items.len()) {
+            return Err(FormatError::Mismatch("mix amounts and items").into());
+        }
+
+        let ingredients = items
+            .into_iter()
+            .zip(total.amounts)
+            .zip(finals)
+            .zip(0..)
+            .map(|(((Item { name, is_flour }, amounts), final_weight), id)| {
+                /* the web app always has every item in the total */
+                let amounts = amounts.ok_or(FormatError::Mismatch("total amounts and items"))?;
+                let inputs = Inputs { final_weight, ..amounts.into() };
+                Ok(Ingredient { id, name, is_flour, inputs })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mixes = mixes
+            .into_iter()
+            .map(|Mix { name, amounts, flour, total }| {
+                let ingredients = amounts
+                    .into_iter()
+                    .zip(0..)
+                    .filter_map(|(amounts, id)| {
+                        Some(MixIngredient { id, inputs: amounts?.into() })
+                    })
+                    .collect();
+                super::Mix { name, total: total.into(), flour: flour.into(), ingredients }
+            })
+            .collect();
+
+        Ok(Document {
+            meta: Meta { title: total.name, ..Default::default() },
+            ingredients,
+            total: total.total.into(),
+            flour: total.flour.into(),
+            mixes,
+        })
+    }
+}
+
+mod v0 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Recipe {
+        pub items: Vec<v1::Item>,
+        pub total: Mix,
+        pub mixes: Vec<Mix>,
+        #[serde(rename = "final")]
+        pub finals: Vec<Value>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Mix {
+        pub name: String,
+        pub amounts: Vec<Option<Amounts>>,
+        pub flour: Amounts,
+        pub total: Amounts,
+    }
+
+    /// zero for what isn't an input
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Amounts {
+        pub weight: Value,
+        pub bakers: Value,
+        pub in_other: Value,
+    }
+
+    fn zero_to_none(v: Value) -> Option<Value> {
+        Some(v).filter(|&v| v != 0.0)
+    }
+
+    impl From<Amounts> for v1::Amounts {
+        fn from(Amounts { weight, bakers, in_other }: Amounts) -> Self {
+            v1::Amounts {
+                weight: zero_to_none(weight),
+                bakers: zero_to_none(bakers),
+                in_other: zero_to_none(in_other),
+            }
+        }
+    }
+
+    impl From<Mix> for v1::Mix {
+        fn from(Mix { name, amounts, flour, total }: Mix) -> Self {
+            v1::Mix {
+                name,
+                amounts: amounts.into_iter().map(|a| a.map(Into::into)).collect(),
+                flour: flour.into(),
+                total: total.into(),
+            }
+        }
+    }
+
+    pub fn to_v1(recipe: Recipe) -> v1::Recipe {
+        let Recipe { items, total, mixes, finals } = recipe;
+        v1::Recipe {
+            items,
+            total: total.into(),
+            mixes: mixes.into_iter().map(Into::into).collect(),
+            finals: finals.into_iter().map(zero_to_none).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* payloads of links shared from the web app, after base64 and lz4, see `share` for the
+     * links themselves */
+    const CORPUS: &[(&str, &str)] = &[
+        ("v0_pizza", include_str!("../corpus/v0_pizza.json")),
+        ("v0_baguette", include_str!("../corpus/v0_baguette.json")),
+        ("v1_pizza", include_str!("../corpus/v1_pizza.json")),
+        ("v1_rye", include_str!("../corpus/v1_rye.json")),
+    ];
+
+    fn corpus(name: &str) -> Document {
+        let (_, s) = CORPUS.iter().find(|(n, _)| *n == name).unwrap();
+        from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_corpus() {
+        for (name, s) in CORPUS {
+            let document = from_str(s).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert!(document.solve().is_ok(), "{name}");
+            assert_eq!(from_str(&to_string(&document)), Ok(document), "{name}");
+        }
+
+        /* the same recipe saved before and after nulls */
+        assert_eq!(corpus("v0_pizza"), corpus("v1_pizza"));
+
+        let pizza = corpus("v1_pizza");
+        assert_eq!(pizza.meta.title, "pizza 🍕 one ball = 240g");
+        assert_eq!(pizza.total, Inputs::weight(960.0));
+        assert_eq!(pizza.ingredients[4].name, "water 🚿");
+        assert_eq!(pizza.ingredients[4].inputs, Inputs::bakers(0.72));
+        assert_eq!(
+            pizza.mixes[0].ingredients,
+            [
+                MixIngredient { id: 0, inputs: Inputs::default() },
+                MixIngredient { id: 4, inputs: Inputs::bakers(2.0) },
+            ]
+        );
+
+        let rye = corpus("v1_rye");
+        assert_eq!(rye.ingredients[2].inputs.final_weight, Some(150.0));
+        assert_eq!(rye.mixes.len(), 2);
+    }
+
+    #[test]
+    fn test_bad() {
+        let pizza = include_str!("../corpus/v1_pizza.json");
+
+        let bad = |s: &str| from_str(s).unwrap_err();
+
+        assert!(matches!(
+            bad("not json"),
+            Error::Format(FormatError::Malformed(_))
+        ));
+        assert!(matches!(
+            bad("[1]"),
+            Error::Format(FormatError::Malformed(_))
+        ));
+        assert_eq!(
+            bad(&pizza.replace(r#""v":1"#, r#""v":3"#)),
+            Error::Format(FormatError::UnknownVersion(3))
+        );
+        assert!(matches!(
+            bad(&pizza.replace(r#""v":1"#, r#""v":"1""#)),
+            Error::Format(FormatError::Malformed(_))
+        ));
+        assert!(matches!(
+            bad(&pizza.replace(r#""is_flour""#, r#""is_floury""#)),
+            Error::Format(FormatError::Malformed(_))
+        ));
+        assert_eq!(
+            bad(&pizza.replace(r#""final":[null,"#, r#""final":["#)),
+            Error::Format(FormatError::Mismatch("final weights and items"))
+        );
+        assert_eq!(
+            bad(&pizza.replace(
+                r#"💦","amounts":[{"weight":null,"bakers":null,"in_other":null},"#,
+                r#"💦","amounts":["#
+            )),
+            Error::Format(FormatError::Mismatch("mix amounts and items"))
+        );
+
+        /* V2 is checked as strictly */
+        let mut document = corpus("v1_pizza");
+        document.mixes[0].ingredients[0].id = 9;
+        assert!(matches!(bad(&to_string(&document)), Error::Invalid(_)));
+
+        /* too big for an f32 */
+        assert_eq!(
+            bad(&pizza.replace(r#""weight":960"#, r#""weight":1e39"#)),
+            Error::Format(FormatError::NotFinite)
+        );
+    }
+}
@@ -1,10 +1,13 @@
 #![allow(unused)]
-// #![no_std]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
 
 // makes bigger binary a tiny bit
 // use smallvec::SmallVec;
 // pub type Vec<T> = SmallVec<[T; 0]>;
 
+use alloc::vec::Vec;
 use core::cmp::PartialEq;
 use core::marker::PhantomData;
 use core::panic::Location;
@@ -37,147 +40,431 @@ macro_rules! derefs {
     };
 }
 
-pub use rules::{Amounts, InMix, Item, Mix, Recipe, WithMixes};
+pub use error::{Error, Result};
+pub use path::{Field, MixPath, Path, Paths, Slot};
+pub use quantity::{Fraction, FractionIdx, Idx, Quantity, Weight, WeightIdx};
+pub use rules::{
+    Amounts, InMix, Ingredient, IngredientId, Item, Mix, Recipe, Solution, WithMixes,
+};
+pub use validate::{Problem, ProblemKind};
+
+mod error;
+mod path;
+mod quantity;
+#[cfg(feature = "serde")]
+mod serialize;
+mod validate;
+
+pub mod document;
+pub mod edit;
+#[cfg(feature = "json")]
+pub mod format;
+#[cfg(feature = "message")]
+pub mod message;
+pub mod reconcile;
+pub mod sensitivity;
+#[cfg(any(feature = "share", feature = "message"))]
+pub mod share;
+pub mod sweep;
 
 pub type Index = u16;
 pub type Value = f32;
-// pub struct Kilograms(Value);
-// pub struct Percent(Value);
 
 const UNSOLVED: f32 = f32::NAN;
-const OVERFLOW: Index = Index::MAX;
 
-pub const OVERFLOW_INDEX: Index = OVERFLOW;
+/// the most values there can be, every index fits in an `Index`
+pub const MAX_VALUES: usize = Index::MAX as usize + 1;
 
 fn is_unsolved(v: f32) -> bool {
     v.is_nan()
 }
 
-fn extend_unsolved<const N: usize>(values: &mut Vec<f32>) -> Option<[Index; N]> {
-    let mut indexes = [0; N];
+/// Where a value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    Unsolved,
+    /// set by the user
+    Input,
+    /// solved by the math at this index in the `Solver`
+    Derived(usize),
+    /// solved by a fallback math at this index in the `Solver`, like a mix's flour being 100%
+    Default(usize),
+    /// zero over zero according to the math at this index in the `Solver`
+    Undefined(usize),
+    /// infinite according to the math at this index in the `Solver`, an error, see
+    /// `Solver::infinite`
+    Infinite(usize),
+    /// solved but not consistent with some math, see `Solver::conflicts`
+    Conflicting,
+    /// not used by anything, see `Values::free`
+    Free,
+}
 
-    if values.len().checked_add(N)? > values.capacity() {
-        return None;
+impl State {
+    /// Input, Derived, Default or Conflicting
+    pub fn is_solved(self) -> bool {
+        !matches!(
+            self,
+            State::Unsolved | State::Undefined(_) | State::Infinite(_) | State::Free
+        )
     }
 
-    indexes
-        .iter_mut()
-        .zip(values.len().try_into().ok()?..)
-        .for_each(|(p, index)| *p = index);
+    /// how many codes there are, every `code` is less than this
+    pub const CODES: u8 = 8;
 
-    values.extend([UNSOLVED; N]);
-
-    Some(indexes)
+    /// a number for each variant, without the math index, for passing around in a `Uint8Array`
+    ///
+    /// 0 unsolved, 1 input, 2 derived, 3 default, 4 undefined, 5 conflicting, 6 free, 7 infinite
+    pub fn code(self) -> u8 {
+        match self {
+            State::Unsolved => 0,
+            State::Input => 1,
+            State::Derived(_) => 2,
+            State::Default(_) => 3,
+            State::Undefined(_) => 4,
+            State::Conflicting => 5,
+            State::Free => 6,
+            State::Infinite(_) => 7,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Values {
     buf: Vec<f32>,
-    did_overflow: Option<usize>,
-    overflow_value: f32,
+    states: Vec<State>,
+    limit: usize,
+    /// sorted, see `edit`
+    pinned: Vec<Index>,
+    /// indexes of free values, reused before growing
+    free: Vec<Index>,
+    /// indexes freed since the last `reset`, not reused until then
+    freed: Vec<Index>,
 }
 
+/// values that aren't unsolved (NaN) are inputs
 impl From<Vec<f32>> for Values {
     fn from(buf: Vec<f32>) -> Values {
-        Values { buf, did_overflow: None, overflow_value: f32::INFINITY }
+        let states = buf
+            .iter()
+            .map(|&v| {
+                if is_unsolved(v) {
+                    State::Unsolved
+                } else {
+                    State::Input
+                }
+            })
+            .collect();
+        Values {
+            buf,
+            states,
+            limit: MAX_VALUES,
+            pinned: default(),
+            free: default(),
+            freed: default(),
+        }
+    }
+}
+
+impl Default for Values {
+    fn default() -> Self {
+        Values::new()
     }
 }
 
 impl Values {
-    pub fn value_index_or_overflow(&mut self) -> Index {
-        let [i] = self.value_indexes_or_overflow();
-        i
+    pub fn new() -> Self {
+        Values::from(Vec::new())
+    }
+
+    /// grows up to `limit` values, or `MAX_VALUES` if that's smaller
+    pub fn with_limit(limit: usize) -> Self {
+        Values { limit: limit.min(MAX_VALUES), ..Values::new() }
     }
 
-    pub fn value_indexes_or_overflow<const N: usize>(&mut self) -> [Index; N] {
-        if let Some(v) = extend_unsolved(&mut self.buf) {
-            v
-        } else {
-            self.did_overflow.get_or_insert(self.buf.len());
-            [OVERFLOW; N]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn value_index(&mut self) -> Result<Index> {
+        let [i] = self.value_indexes()?;
+        Ok(i)
+    }
+
+    /// `N` unsolved values, ones freed before the last `reset` first then new ones, or none of
+    /// them if that goes over the limit
+    pub fn value_indexes<const N: usize>(&mut self) -> Result<[Index; N]> {
+        let start = self.buf.len();
+        let reused = N.min(self.free.len());
+
+        if start + N - reused > self.limit {
+            return Err(Error::Capacity { limit: self.limit });
         }
+
+        let mut indexes = [0; N];
+        let (old, new) = indexes.split_at_mut(reused);
+
+        for p in old {
+            *p = self.free.pop().expect("N.min(free.len())");
+            self.buf[*p as usize] = UNSOLVED;
+            self.states[*p as usize] = State::Unsolved;
+        }
+
+        new.iter_mut()
+            .zip(start..)
+            .for_each(|(p, index)| *p = index as Index);
+
+        self.buf.resize(start + N - reused, UNSOLVED);
+        self.states.resize(self.buf.len(), State::Unsolved);
+
+        Ok(indexes)
     }
 
-    /// can be determined if we ran out of room to allocate more values
+    /// marks the value free so that its index is given out again by `value_indexes` after the
+    /// next `reset`
     ///
-    /// notably, overflow is not set if an invalid index was used merely to retrieve a value
-    pub fn did_overflow(&self) -> bool {
-        self.did_overflow.is_some()
+    /// Until it's given out, using the index is an `Error::Stale`. Nothing notices if it's used
+    /// after that, so remove whatever uses it from the recipe before solving again; like with
+    /// `Recipe::remove_ingredient` and `Recipe::remove_mix`.
+    pub fn free(&mut self, i: impl Into<Index>) {
+        let i = i.into();
+        match self.states.get_mut(i as usize) {
+            Some(state) if *state != State::Free => {
+                *state = State::Free;
+                self.buf[i as usize] = UNSOLVED;
+                self.freed.push(i);
+                if let Ok(at) = self.pinned.binary_search(&i) {
+                    self.pinned.remove(at);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn free_item(&mut self, item: &rules::Item) {
+        item.fields().for_each(|(_, i)| self.free(i));
+    }
+
+    /// every item in the mix
+    pub fn free_mix(&mut self, mix: &rules::Mix) {
+        mix.slots().for_each(|(_, item)| self.free_item(item));
+    }
+
+    /// number of values, including free ones
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+/// Pinned values are held when editing another input, see `edit::edit`.
+///
+/// Pinning or unpinning a free value is an `Error::Stale`, like with `value_mut`.
+impl Values {
+    pub fn pin(&mut self, i: impl Into<Index>) -> Result<()> {
+        let i = self.check(i.into())?;
+        if let Err(at) = self.pinned.binary_search(&i) {
+            self.pinned.insert(at, i);
+        }
+        Ok(())
+    }
+
+    pub fn unpin(&mut self, i: impl Into<Index>) -> Result<()> {
+        let i = self.check(i.into())?;
+        if let Ok(at) = self.pinned.binary_search(&i) {
+            self.pinned.remove(at);
+        }
+        Ok(())
+    }
+
+    pub fn is_pinned(&self, i: impl Into<Index>) -> bool {
+        self.pinned.binary_search(&i.into()).is_ok()
     }
 
-    pub fn how_overflow(&self) -> Option<usize> {
-        self.did_overflow.clone()
+    pub fn pinned(&self) -> &[Index] {
+        self.pinned.as_slice()
     }
 }
 
 impl Values {
-    pub fn value_opt(&self, i: Index) -> Option<Value> {
-        self.buf.get(i as usize).cloned()
+    /// `None` if `i` isn't in here or is free
+    pub fn value_opt(&self, i: impl Into<Index>) -> Option<Value> {
+        let i = i.into();
+        match self.state(i) {
+            State::Free => None,
+            _ => self.buf.get(i as usize).cloned(),
+        }
+    }
+
+    /// unsolved if `i` isn't in here
+    pub fn value(&self, i: impl Into<Index>) -> Value {
+        self.value_opt(i).unwrap_or(UNSOLVED)
+    }
+
+    /// like `value`, but as the quantity the index is for
+    pub fn get<K: Quantity>(&self, i: Idx<K>) -> K {
+        K::from_value(self.value(i))
     }
 
-    pub fn value(&self, i: Index) -> Value {
-        if let Some(v) = self.buf.get(i as usize) {
-            *v
-        } else {
-            self.overflow_value
+    /// `i` if it's in here and not free
+    fn check(&self, i: Index) -> Result<Index> {
+        match self.states.get(i as usize) {
+            Some(State::Free) => Err(Error::Stale(i)),
+            Some(_) => Ok(i),
+            None => Err(Error::InvalidIndex(i)),
         }
     }
 
-    pub fn value_mut(&mut self, i: Index) -> &mut Value {
-        if let Some(v) = self.buf.get_mut(i as usize) {
-            v
-        } else {
-            self.did_overflow.get_or_insert(i as usize);
-            &mut self.overflow_value
+    /// marks the value as an input
+    pub fn value_mut(&mut self, i: impl Into<Index>) -> Result<&mut Value> {
+        let i = i.into();
+        match (
+            self.buf.get_mut(i as usize),
+            self.states.get_mut(i as usize),
+        ) {
+            (Some(_), Some(State::Free)) => Err(Error::Stale(i)),
+            (Some(v), Some(state)) => {
+                *state = State::Input;
+                Ok(v)
+            }
+            _ => Err(Error::InvalidIndex(i)),
         }
     }
 
-    pub fn minimal_recipe(&mut self) -> rules::Recipe {
-        rules::Recipe {
+    pub fn state(&self, i: impl Into<Index>) -> State {
+        self.states
+            .get(i.into() as usize)
+            .cloned()
+            .unwrap_or(State::Unsolved)
+    }
+
+    pub fn states(&self) -> &[State] {
+        self.states.as_slice()
+    }
+
+    /// every value by index, unsolved and free values are NaN
+    pub fn as_slice(&self) -> &[Value] {
+        self.buf.as_slice()
+    }
+
+    /// indexes of values set by the user
+    pub fn inputs(&self) -> impl Iterator<Item = Index> + '_ {
+        (0..self.states.len())
+            .filter(|&i| self.states[i] == State::Input)
+            .map(|i| i as Index)
+    }
+
+    /// makes the value unsolved, whether it was an input or not
+    pub fn clear(&mut self, i: impl Into<Index>) -> Result<()> {
+        let i = self.check(i.into())?;
+        self.set(i, UNSOLVED, State::Unsolved);
+        Ok(())
+    }
+
+    /// clears every value that isn't an input, so that they can be solved again
+    ///
+    /// Values freed since the last reset can be given out again after this.
+    pub fn reset(&mut self) {
+        self.free.append(&mut self.freed);
+
+        for i in 0..self.buf.len() {
+            if !matches!(self.states[i], State::Input | State::Free) {
+                self.buf[i] = UNSOLVED;
+                self.states[i] = State::Unsolved;
+            }
+        }
+    }
+
+    /* these leave free values alone, so that solving with a stale index doesn't take a value
+     * that's still in the free list */
+
+    /// changes the value without changing its state
+    pub(crate) fn adjust(&mut self, i: Index, v: Value) {
+        if let (Some(value), Some(s)) = (self.buf.get_mut(i as usize), self.states.get(i as usize))
+        {
+            if *s != State::Free {
+                *value = v;
+            }
+        }
+    }
+
+    pub(crate) fn set(&mut self, i: Index, v: Value, state: State) {
+        if let (Some(value), Some(s)) = (
+            self.buf.get_mut(i as usize),
+            self.states.get_mut(i as usize),
+        ) {
+            if *s != State::Free {
+                *value = v;
+                *s = state;
+            }
+        }
+    }
+
+    pub(crate) fn set_state(&mut self, i: Index, state: State) {
+        if let Some(s) = self.states.get_mut(i as usize) {
+            if *s != State::Free {
+                *s = state;
+            }
+        }
+    }
+
+    pub fn minimal_recipe(&mut self) -> Result<rules::Recipe> {
+        Ok(rules::Recipe {
             dough: rules::Mix {
-                total: self.new_item().into(),
-                flour: self.new_item().into(),
-                nonflour: self.new_item().into(),
+                total: self.new_item()?.into(),
+                flour: self.new_item()?.into(),
+                nonflour: self.new_item()?.into(),
                 flours: default(),
                 nonflours: default(),
             },
             mixes: default(),
-        }
+        })
     }
 
-    pub fn minimal_mix(&mut self) -> rules::Mix {
-        rules::Mix {
-            total: self.new_mix_item().into(),
-            flour: self.new_mix_item().into(),
-            nonflour: self.new_mix_item().into(),
+    pub fn minimal_mix(&mut self) -> Result<rules::Mix> {
+        Ok(rules::Mix {
+            total: self.new_mix_item()?.into(),
+            flour: self.new_mix_item()?.into(),
+            nonflour: self.new_mix_item()?.into(),
             flours: default(),
             nonflours: default(),
-        }
+        })
     }
 
-    pub fn new_item(&mut self) -> rules::WithMixes {
+    pub fn new_item(&mut self) -> Result<rules::WithMixes> {
         let [weight, bakers, weight_in_mixes, weight_less_mixes, percent_in_mixes, percent_less_mixes] =
-            self.value_indexes_or_overflow();
-        let amounts = rules::Amounts { weight, bakers };
-        rules::WithMixes {
+            self.value_indexes()?;
+        let amounts = rules::Amounts { weight: Idx::new(weight), bakers: Idx::new(bakers) };
+        Ok(rules::WithMixes {
             amounts,
-            weight_in_mixes,
-            weight_less_mixes,
-            percent_in_mixes,
-            percent_less_mixes,
-        }
+            weight_in_mixes: Idx::new(weight_in_mixes),
+            weight_less_mixes: Idx::new(weight_less_mixes),
+            percent_in_mixes: Idx::new(percent_in_mixes),
+            percent_less_mixes: Idx::new(percent_less_mixes),
+        })
     }
 
-    pub fn new_mix_item(&mut self) -> rules::InMix {
-        let [weight, bakers, percent_of_total] = self.value_indexes_or_overflow();
-        let amounts = rules::Amounts { weight, bakers };
-        rules::InMix { amounts, percent_of_total }
+    pub fn new_mix_item(&mut self) -> Result<rules::InMix> {
+        let [weight, bakers, percent_of_total] = self.value_indexes()?;
+        let amounts = rules::Amounts { weight: Idx::new(weight), bakers: Idx::new(bakers) };
+        Ok(rules::InMix { amounts, percent_of_total: Idx::new(percent_of_total) })
     }
 }
 
 mod rules {
-    use super::{Index, ToWhence, Value, Values, Whence};
+    use super::{
+        is_unsolved, Error, FractionIdx, Idx, Index, Paths, Result, ToWhence, Value, Values,
+        WeightIdx, Whence,
+    };
 
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
     use core::iter::once;
 
     /* Each item used in a table has:
@@ -194,50 +481,109 @@ mod rules {
      *   (item's weight in this mix / item weight in totals table)
      */
 
+    /* An ingredient in a mix is the same ingredient as the one in the dough with the same id,
+     * in the same list (flours or nonflours). Mixes only list the ingredients they use, in any
+     * order. */
+
     #[derive(Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Recipe {
         pub dough: Mix,
         pub mixes: Vec<Mix>,
     }
 
     #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Mix {
         pub total: Item,
         pub flour: Item,
         pub nonflour: Item,
-        pub flours: Vec<Option<Item>>,
-        pub nonflours: Vec<Option<Item>>,
+        pub flours: Vec<Ingredient>,
+        pub nonflours: Vec<Ingredient>,
     }
 
+    pub type IngredientId = u32;
+
     #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Ingredient {
+        pub id: IngredientId,
+        pub item: Item,
+    }
+
+    impl Ingredient {
+        pub fn new(id: IngredientId, item: impl Into<Item>) -> Self {
+            Ingredient { id, item: item.into() }
+        }
+    }
+
+    /* removing frees the values of what's removed, other indexes stay as they are */
+
+    impl Recipe {
+        /// removes the ingredient from the dough and every mix
+        pub fn remove_ingredient(&mut self, id: IngredientId, values: &mut Values) {
+            once(&mut self.dough)
+                .chain(self.mixes.iter_mut())
+                .for_each(|mix| mix.remove_ingredient(id, values));
+        }
+
+        /// later mixes move down a position, an `Error::Argument` if there's no mix at `i`
+        pub fn remove_mix(&mut self, i: usize, values: &mut Values) -> Result<()> {
+            if i >= self.mixes.len() {
+                return Err(Error::Argument("no mix at that position"));
+            }
+            values.free_mix(&self.mixes.remove(i));
+            Ok(())
+        }
+    }
+
+    impl Mix {
+        pub fn remove_ingredient(&mut self, id: IngredientId, values: &mut Values) {
+            for list in [&mut self.flours, &mut self.nonflours] {
+                list.retain(|ingredient| {
+                    let keep = ingredient.id != id;
+                    if !keep {
+                        values.free_item(&ingredient.item);
+                    }
+                    keep
+                });
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum Item {
         WithMixes(WithMixes),
         InMix(InMix),
     }
 
     #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct WithMixes {
         pub amounts: Amounts,
         // sum of weights of this item in mixes
-        pub weight_in_mixes: Index,
+        pub weight_in_mixes: WeightIdx,
         // weight - sum of weights of this item in mixes (aka final)
-        pub weight_less_mixes: Index,
+        pub weight_less_mixes: WeightIdx,
         /// percentage of this item's total weight from mixes
-        pub percent_in_mixes: Index,
+        pub percent_in_mixes: FractionIdx,
         /// percentage of this item's total weight not from mixes
-        pub percent_less_mixes: Index,
+        pub percent_less_mixes: FractionIdx,
     }
 
     #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct InMix {
         pub amounts: Amounts,
-        pub percent_of_total: Index,
+        pub percent_of_total: FractionIdx,
     }
 
     #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Amounts {
-        pub weight: Index,
-        pub bakers: Index,
+        pub weight: WeightIdx,
+        pub bakers: FractionIdx,
     }
 
     derefs!(WithMixes => amounts: Amounts);
@@ -288,24 +634,20 @@ mod rules {
 
         once(dough.flour.bakers)
             .chain(mixes.iter().map(|mix| mix.flour.bakers))
-            .map(|index| Math::TotalFlourBakers100 { index }.to_whence())
+            .map(|index| Math::flour_bakers_100(index).to_whence())
     }
 
     pub(crate) fn for_recipe(recipe: &Recipe) -> impl Iterator<Item = Whence<Math>> + '_ {
         let Recipe { dough, mixes } = recipe;
 
-        return once(dough).chain(mixes).flat_map(for_mix).chain(
+        once(dough).chain(mixes).flat_map(for_mix).chain(
             /* iterate each row/item in total and where it's mixed */
-            [Key::Total, Key::Flour, Key::NonFlour]
-                .into_iter()
-                .chain((0..dough.flours.len()).map(Key::Flours))
-                .chain((0..dough.nonflours.len()).map(Key::NonFlours))
-                .filter_map(|item_key| {
+            dough
+                .slots()
+                .filter_map(|(slot, item)| {
                     Some((
-                        dough.get(item_key)?.with_mixes()?,
-                        mixes
-                            .iter()
-                            .filter_map(move |mix| mix.get(item_key)?.in_mix()),
+                        item.with_mixes()?,
+                        mixes.iter().filter_map(move |mix| mix.item(slot)?.in_mix()),
                     ))
                 })
                 .flat_map(|(t, mixed)| {
@@ -323,37 +665,10 @@ mod rules {
                     ]
                     .into_iter()
                     .chain(mixed.map(|i| {
-                        Math::PercentOf {
-                            product: i.weight,
-                            pct: i.percent_of_total,
-                            of: t.weight,
-                        }
-                        .to_whence()
+                        Math::percent_of(i.weight, i.percent_of_total, t.weight).to_whence()
                     }))
                 }),
-        );
-
-        #[derive(Debug, Copy, Clone)]
-        enum Key {
-            Total,
-            Flour,
-            NonFlour,
-            Flours(usize),
-            NonFlours(usize),
-        }
-
-        impl Mix {
-            fn get(&self, key: Key) -> Option<&Item> {
-                use Key::*;
-                match key {
-                    Total => Some(&self.total),
-                    Flour => Some(&self.flour),
-                    NonFlour => Some(&self.nonflour),
-                    Flours(i) => self.flours.get(i)?.as_ref(),
-                    NonFlours(i) => self.nonflours.get(i)?.as_ref(),
-                }
-            }
-        }
+        )
     }
 
     fn for_mix(mix: &Mix) -> impl Iterator<Item = Whence<Math>> + '_ {
@@ -366,15 +681,13 @@ mod rules {
                 .to_whence(),
             flours
                 .iter()
-                .flatten()
-                .map(core::ops::Deref::deref)
+                .map(|ingredient| &*ingredient.item)
                 .map(|&Amounts { weight, .. }| weight)
                 .sums_to(flour.weight)
                 .to_whence(),
             nonflours
                 .iter()
-                .flatten()
-                .map(core::ops::Deref::deref)
+                .map(|ingredient| &*ingredient.item)
                 .map(|&Amounts { weight, .. }| weight)
                 .sums_to(nonflour.weight)
                 .to_whence(),
@@ -384,15 +697,13 @@ mod rules {
                 .to_whence(),
             flours
                 .iter()
-                .flatten()
-                .map(core::ops::Deref::deref)
+                .map(|ingredient| &*ingredient.item)
                 .map(|&Amounts { bakers, .. }| bakers)
                 .sums_to(flour.bakers)
                 .to_whence(),
             nonflours
                 .iter()
-                .flatten()
-                .map(core::ops::Deref::deref)
+                .map(|ingredient| &*ingredient.item)
                 .map(|&Amounts { bakers, .. }| bakers)
                 .sums_to(nonflour.bakers)
                 .to_whence(),
@@ -402,11 +713,11 @@ mod rules {
             /* bakers percentages as expression of total flour weight */
             [total, flour, nonflour]
                 .into_iter()
-                .chain(flours.iter().flatten())
-                .chain(nonflours.iter().flatten())
+                .chain(flours.iter().map(|ingredient| &ingredient.item))
+                .chain(nonflours.iter().map(|ingredient| &ingredient.item))
                 .map(core::ops::Deref::deref)
                 .map(|&Amounts { weight, bakers, .. }| {
-                    Math::PercentOf { product: weight, pct: bakers, of: flour.weight }.to_whence()
+                    Math::percent_of(weight, bakers, flour.weight).to_whence()
                 }),
         )
         .chain(
@@ -415,8 +726,8 @@ mod rules {
             /* also its percent_in_mixes and percent_less_mixes are ratios of its weight */
             [total, flour, nonflour]
                 .into_iter()
-                .chain(flours.iter().flatten())
-                .chain(nonflours.iter().flatten())
+                .chain(flours.iter().map(|ingredient| &ingredient.item))
+                .chain(nonflours.iter().map(|ingredient| &ingredient.item))
                 .flat_map(|i| i.with_mixes())
                 .flat_map(
                     |&WithMixes {
@@ -431,18 +742,10 @@ mod rules {
                             [weight_in_mixes, weight_less_mixes]
                                 .sums_to(weight)
                                 .to_whence(),
-                            Math::PercentOf {
-                                product: weight_in_mixes,
-                                pct: percent_in_mixes,
-                                of: weight,
-                            }
-                            .to_whence(),
-                            Math::PercentOf {
-                                product: weight_less_mixes,
-                                pct: percent_less_mixes,
-                                of: weight,
-                            }
-                            .to_whence(),
+                            Math::percent_of(weight_in_mixes, percent_in_mixes, weight)
+                                .to_whence(),
+                            Math::percent_of(weight_less_mixes, percent_less_mixes, weight)
+                                .to_whence(),
                         ]
                     },
                 ),
@@ -451,6 +754,8 @@ mod rules {
 
     pub type Summands = Box<[Index]>;
 
+    const CHECK_TOLERANCE: f64 = 1e-5;
+
     #[derive(Debug)]
     pub enum Math {
         Sum {
@@ -468,7 +773,87 @@ mod rules {
         },
     }
 
+    /// What `Math::solve_for` found.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Solution {
+        Value(Value),
+        /// zero over zero
+        Undefined,
+        /// not zero over zero, or something involving infinity
+        Infinite,
+        /// the value isn't in the Math, or something else in it isn't solved
+        Unsolved,
+    }
+
+    impl Solution {
+        pub fn value(self) -> Option<Value> {
+            match self {
+                Solution::Value(v) => Some(v),
+                _ => None,
+            }
+        }
+
+        /// for arithmetic on solved values, anything that isn't finite came from infinity
+        fn finite(v: Value) -> Self {
+            if v.is_finite() {
+                Solution::Value(v)
+            } else {
+                Solution::Infinite
+            }
+        }
+
+        fn sum(terms: impl Iterator<Item = Value>) -> Self {
+            let mut sum = 0.0;
+            for term in terms {
+                if is_unsolved(term) {
+                    return Solution::Unsolved;
+                }
+                sum += term;
+            }
+            Solution::finite(sum)
+        }
+
+        fn product(a: Value, b: Value) -> Self {
+            if is_unsolved(a) || is_unsolved(b) {
+                Solution::Unsolved
+            } else {
+                Solution::finite(a * b)
+            }
+        }
+
+        fn ratio(numerator: Value, denominator: Value) -> Self {
+            if is_unsolved(numerator) || is_unsolved(denominator) {
+                Solution::Unsolved
+            } else if denominator == 0.0 && numerator == 0.0 {
+                Solution::Undefined
+            } else {
+                Solution::finite(numerator / denominator)
+            }
+        }
+    }
+
+    /// These are the only way `rules` builds maths, so that the kinds of values line up.
     impl Math {
+        /// `product` is `pct` of `of`
+        pub fn percent_of(product: WeightIdx, pct: FractionIdx, of: WeightIdx) -> Self {
+            Math::PercentOf { product: product.into(), pct: pct.into(), of: of.into() }
+        }
+
+        pub fn flour_bakers_100(index: FractionIdx) -> Self {
+            Math::TotalFlourBakers100 { index: index.into() }
+        }
+    }
+
+    impl Math {
+        /// the name of the variant, like `percent_of`
+        pub fn kind(&self) -> &'static str {
+            match self {
+                Math::Sum { .. } => "sum",
+                Math::PercentOf { .. } => "percent_of",
+                Math::TotalFlourBakers100 { .. } => "total_flour_bakers_100",
+            }
+        }
+
         pub fn indexes(&self) -> impl Iterator<Item = Index> + '_ {
             /* this just here to do compiler error if a variant is added but you forgor to add
              * it below :) */
@@ -505,73 +890,102 @@ mod rules {
                 .cloned()
         }
 
-        /// May return an unsolved value for some PercentOf operations.
+        /// Solving may not work out even if everything else in the Math is solved.
         ///
-        /// Things like 0.0 / 0.0 or Inf * 0.0 will evaluate to NaN, the unsolved value. So
-        /// this can return an unsolved value even if none of the value in the expression are
-        /// unsolved. So this is kind a fallible that way.
+        /// A percentage of zero, like the baker's percentage of a flour in an empty mix, is
+        /// `Solution::Undefined`. A percentage of zero that isn't zero itself, or anything
+        /// involving infinity, is `Solution::Infinite`.
         ///
-        /// If that happens, just discard this rule as you would otherwise but don't treat the
-        /// value as solved and maybe there's a better Math somewhere else that will solve for
-        /// the value you want.
-        pub fn solve_for(&self, solve_for: Index, values: &Values) -> Value {
+        /// Either way, don't treat the value as solved. Maybe there's a better Math somewhere
+        /// else that will solve for the value you want.
+        pub fn solve_for(&self, solve_for: Index, values: &Values) -> Solution {
             let value = |i| values.value(i);
 
-            match self {
-                &Math::Sum { sum, ref ands } => {
+            match *self {
+                Math::Sum { sum, ref ands } => {
                     if solve_for == sum {
-                        ands.iter().cloned().map(value).sum()
+                        Solution::sum(ands.iter().cloned().map(value))
                     } else if ands.contains(&solve_for) {
-                        value(sum)
-                            - ands
-                                .iter()
-                                .cloned()
-                                .filter(|&i| solve_for != i)
-                                .map(value)
-                                .sum::<f32>()
+                        let rest = ands.iter().cloned().filter(|&i| solve_for != i);
+                        match Solution::sum(rest.map(value)) {
+                            Solution::Value(rest) => {
+                                Solution::sum([value(sum), -rest].into_iter())
+                            }
+                            otherwise => otherwise,
+                        }
                     } else {
-                        super::UNSOLVED
+                        Solution::Unsolved
                     }
                 }
-                &Math::PercentOf { product, pct, of } => {
+                Math::PercentOf { product, pct, of } => {
                     if solve_for == product {
-                        value(pct) * value(of)
+                        Solution::product(value(pct), value(of))
                     } else if solve_for == pct {
-                        value(product) / value(of)
+                        Solution::ratio(value(product), value(of))
                     } else if solve_for == of {
-                        value(product) / value(pct)
+                        Solution::ratio(value(product), value(pct))
                     } else {
-                        super::UNSOLVED
+                        Solution::Unsolved
                     }
                 }
 
-                &Math::TotalFlourBakers100 { index } => {
+                Math::TotalFlourBakers100 { index } => {
                     if solve_for == index {
-                        1.0
+                        Solution::Value(1.0)
                     } else {
-                        super::UNSOLVED
+                        Solution::Unsolved
                     }
                 }
             }
         }
 
         /// true iff solved and the values are consistent with this Math.
+        ///
+        /// Consistent within a relative error of `CHECK_TOLERANCE`, since values solved in f32
+        /// hardly ever add up exactly.
         pub fn check(&self, values: &Values) -> bool {
-            let value = |i| values.value(i);
+            let value = |i| values.value(i) as f64;
+            let scale = self.indexes().map(|i| value(i).abs()).fold(1.0, f64::max);
+            self.residual(value).abs() <= CHECK_TOLERANCE * scale
+        }
 
-            match self {
-                &Math::Sum { sum, ref ands } => {
-                    value(sum) == ands.iter().cloned().map(value).sum::<f32>()
+        /// How far the values are from satisfying this Math, zero if they do.
+        ///
+        /// Unlike `check`, this is continuous so that it can be minimized, see `reconcile`.
+        pub fn residual(&self, value: impl Fn(Index) -> f64) -> f64 {
+            match *self {
+                Math::Sum { sum, ref ands } => {
+                    value(sum) - ands.iter().cloned().map(&value).sum::<f64>()
+                }
+                Math::PercentOf { product, pct, of } => value(product) - value(pct) * value(of),
+                Math::TotalFlourBakers100 { index } => value(index) - 1.0,
+            }
+        }
+
+        /// Calls `f` with each index and the partial derivative of `residual` for it.
+        ///
+        /// An index can occur more than once in a Math, like a flour weight being the product
+        /// and the base of its own baker's percentage. It's passed to `f` once per occurrence, so
+        /// sum them up.
+        pub fn partials(&self, value: impl Fn(Index) -> f64, mut f: impl FnMut(Index, f64)) {
+            match *self {
+                Math::Sum { sum, ref ands } => {
+                    f(sum, 1.0);
+                    ands.iter().for_each(|&i| f(i, -1.0));
                 }
-                // TODO this floating point comparison probably needs some kind of error margin
-                &Math::PercentOf { product, pct, of } => value(product) == value(pct) * value(of),
-                &Math::TotalFlourBakers100 { index } => 1.0 == value(index),
+                Math::PercentOf { product, pct, of } => {
+                    f(product, 1.0);
+                    f(pct, -value(of));
+                    f(of, -value(pct));
+                }
+                Math::TotalFlourBakers100 { index } => f(index, 1.0),
             }
         }
 
         // #[cfg(test)]
         #[cfg(debug_assertions)]
-        pub fn display(&self, values: &Values) -> impl core::fmt::Display {
+        /// like `pct dough.nonflours[0].weight=700 dough.nonflours[0].bakers=0.7 ...`
+        pub fn display(&self, values: &Values, paths: &Paths) -> impl core::fmt::Display {
             once(match self {
                 Math::Sum { .. } => "sum".to_string(),
                 Math::PercentOf { .. } => "pct".to_string(),
@@ -579,72 +993,90 @@ mod rules {
             })
             .chain(
                 self.indexes()
-                    .map(|i| format!(" {}[{}]", values.value(i), i)),
+                    .map(|i| format!(" {}", paths.display_value(i, values))),
             )
             .collect::<String>()
         }
     }
 
+    #[cfg(test)]
+    fn w(index: Index) -> WeightIdx {
+        Idx::new(index)
+    }
+
     #[test]
     fn test_math_solve_sum() {
-        use super::is_unsolved;
+        use Solution::*;
 
         let values = Values::from((0..16).map(|n| n as f32).collect::<Vec<_>>());
 
-        assert_eq!(0.0, [].sums_to(0).solve_for(0, &values));
-        assert_eq!(6.0, [2, 4].sums_to(0).solve_for(0, &values));
-        assert_eq!(5.0, [3, 0].sums_to(8).solve_for(0, &values));
+        assert_eq!(Value(0.0), [].sums_to(w(0)).solve_for(0, &values));
+        assert_eq!(Value(6.0), [w(2), w(4)].sums_to(w(0)).solve_for(0, &values));
+        assert_eq!(Value(5.0), [w(3), w(0)].sums_to(w(8)).solve_for(0, &values));
+
+        assert_eq!(Unsolved, [].sums_to(w(5)).solve_for(0, &values));
+
+        let values = Values::from(vec![super::UNSOLVED, 1.0, f32::INFINITY]);
 
-        assert!(is_unsolved([].sums_to(5).solve_for(0, &values)));
+        assert_eq!(Unsolved, [w(0), w(1)].sums_to(w(2)).solve_for(2, &values));
+        assert_eq!(Infinite, [w(1), w(2)].sums_to(w(0)).solve_for(0, &values));
     }
 
     #[test]
     fn test_math_solve_pct() {
         use super::UNSOLVED;
         use Math::PercentOf;
+        use Solution::*;
 
         assert_eq!(
-            0.400,
+            Value(0.400),
             PercentOf { product: 0, pct: 1, of: 2 }
                 .solve_for(0, &Values::from(vec![UNSOLVED, 0.50, 0.800]))
         );
         assert_eq!(
-            5.000,
+            Value(5.000),
             PercentOf { product: 0, pct: 1, of: 2 }
                 .solve_for(1, &Values::from(vec![2.500, UNSOLVED, 0.500]))
         );
         assert_eq!(
-            0.40,
+            Value(0.40),
             PercentOf { product: 0, pct: 1, of: 2 }
                 .solve_for(2, &Values::from(vec![1.000, 2.500, UNSOLVED]))
         );
 
         assert_eq!(
-            0.0,
+            Value(0.0),
             PercentOf { product: 0, pct: 1, of: 2 }
                 .solve_for(1, &Values::from(vec![0.0, UNSOLVED, 1.0]))
         );
         assert_eq!(
-            0.0,
+            Infinite,
             PercentOf { product: 0, pct: 1, of: 2 }
-                .solve_for(1, &Values::from(vec![0.0, UNSOLVED, 1.0]))
+                .solve_for(1, &Values::from(vec![2.0, UNSOLVED, -0.0]))
         );
         assert_eq!(
-            f32::NEG_INFINITY,
+            Infinite,
             PercentOf { product: 0, pct: 1, of: 2 }
-                .solve_for(1, &Values::from(vec![2.0, UNSOLVED, -0.0]))
+                .solve_for(0, &Values::from(vec![UNSOLVED, f32::INFINITY, 0.0]))
         );
 
-        /* not exactly intended behaviour but something to be aware of */
-        assert!(PercentOf { product: 0, pct: 1, of: 2 }
-            .solve_for(1, &Values::from(vec![0.0, UNSOLVED, 0.0]))
-            .is_nan());
-        assert!(PercentOf { product: 0, pct: 1, of: 2 }
-            .solve_for(1, &Values::from(vec![0.0, UNSOLVED, 0.0]))
-            .is_nan());
-        assert!(PercentOf { product: 0, pct: 1, of: 2 }
-            .solve_for(1, &Values::from(vec![UNSOLVED, f32::INFINITY, 0.0]))
-            .is_nan());
+        /* a percentage of nothing */
+        assert_eq!(
+            Undefined,
+            PercentOf { product: 0, pct: 1, of: 2 }
+                .solve_for(1, &Values::from(vec![0.0, UNSOLVED, 0.0]))
+        );
+        assert_eq!(
+            Undefined,
+            PercentOf { product: 0, pct: 1, of: 2 }
+                .solve_for(2, &Values::from(vec![0.0, 0.0, UNSOLVED]))
+        );
+
+        assert_eq!(
+            Unsolved,
+            PercentOf { product: 0, pct: 1, of: 2 }
+                .solve_for(1, &Values::from(vec![UNSOLVED, f32::INFINITY, 0.0]))
+        );
     }
 
     #[test]
@@ -653,12 +1085,12 @@ mod rules {
 
         let values = Values::from((0..16).map(|n| n as f32).collect::<Vec<_>>());
 
-        assert!([].sums_to(0).check(&values));
-        assert!([1, 2].sums_to(3).check(&values));
+        assert!([].sums_to(w(0)).check(&values));
+        assert!([w(1), w(2)].sums_to(w(3)).check(&values));
 
-        assert!(![1, 2].sums_to(4).check(&values));
-        assert!(![0, 1]
-            .sums_to(2)
+        assert!(![w(1), w(2)].sums_to(w(4)).check(&values));
+        assert!(![w(0), w(1)]
+            .sums_to(w(2))
             .check(&Values::from(vec![0.0, 1.0, UNSOLVED])));
     }
 
@@ -676,17 +1108,18 @@ mod rules {
             .check(&Values::from(vec![1.0, UNSOLVED, 1.5])));
     }
 
-    trait SumsTo {
-        fn sums_to(self, _: Index) -> Math;
+    /// only values of the same kind can be summed
+    trait SumsTo<K> {
+        fn sums_to(self, _: Idx<K>) -> Math;
     }
 
-    impl<I> SumsTo for I
+    impl<I, K> SumsTo<K> for I
     where
-        I: IntoIterator<Item = Index>,
+        I: IntoIterator<Item = Idx<K>>,
     {
-        fn sums_to(self, sum: Index) -> Math {
-            let ands = self.into_iter().collect::<Summands>();
-            Math::Sum { sum, ands }
+        fn sums_to(self, sum: Idx<K>) -> Math {
+            let ands = self.into_iter().map(Index::from).collect::<Summands>();
+            Math::Sum { sum: sum.into(), ands }
         }
     }
 
@@ -719,8 +1152,9 @@ mod rules {
 
 pub mod solve {
     use super::rules::Recipe;
-    use super::{is_unsolved, rules, Index, Value, Values, Whence};
+    use super::{default, rules, Error, Index, Result, Solution, State, Value, Values, Whence};
 
+    use alloc::vec::Vec;
     use core::borrow::BorrowMut;
 
     #[derive(Debug)]
@@ -730,6 +1164,16 @@ pub mod solve {
         maths_by_index_to_solve: Vec<usize>,
         // unsolved value indexes paired with indexes in maths where those values are used
         unsolved_value_to_math_index_pairs: Vec<(Index, usize)>,
+        // value indexes that some math found to be zero over zero, paired with that math
+        undefined: Vec<(Index, usize)>,
+        // value indexes that some math found to be infinite, paired with that math
+        infinite: Vec<(Index, usize)>,
+        // indexes in maths that aren't consistent with the values, found once nothing is left
+        // to solve
+        conflicts: Vec<usize>,
+        checked: bool,
+        // maths before this index in maths are fallbacks
+        fallbacks: usize,
     }
 
     #[derive(Debug)]
@@ -750,13 +1194,17 @@ pub mod solve {
             /* fallback rules are iterated first so that they are popped last from
              * maths_by_index_to_solve */
 
-            for math in rules::for_recipe_fallback(&recipe).chain(rules::for_recipe(&recipe)) {
+            let fallbacks = rules::for_recipe_fallback(recipe).count();
+
+            /* maths without anything to solve are kept too, they're checked for conflicts */
+
+            for math in rules::for_recipe_fallback(recipe).chain(rules::for_recipe(recipe)) {
                 let math_index = maths.len();
                 let last_pairs_len = unsolved_value_to_math_index_pairs.len();
 
                 unsolved_value_to_math_index_pairs.extend(
                     math.indexes()
-                        .filter(|&value_index| is_unsolved(values.value(value_index)))
+                        .filter(|&value_index| !values.state(value_index).is_solved())
                         .map(|value_index| (value_index, math_index)),
                 );
 
@@ -766,12 +1214,35 @@ pub mod solve {
                     maths_by_index_to_solve.push(math_index);
                 }
 
-                if unsolved > 0 {
-                    maths.push(MathToSolve { math, unsolved });
+                maths.push(MathToSolve { math, unsolved });
+            }
+
+            Self {
+                maths,
+                maths_by_index_to_solve,
+                unsolved_value_to_math_index_pairs,
+                undefined: default(),
+                infinite: default(),
+                conflicts: default(),
+                checked: false,
+                fallbacks,
+            }
+        }
+
+        /// like `new` but fails with `Error::Invalid` if the recipe doesn't `validate`, or with
+        /// `Error::InvalidIndex` or `Error::Stale` if it uses an index `values` doesn't have
+        pub fn validated(recipe: &Recipe, values: &Values) -> Result<Self> {
+            recipe.validate()?;
+
+            for (_, index) in recipe.paths() {
+                match values.states().get(index as usize) {
+                    None => return Err(Error::InvalidIndex(index)),
+                    Some(State::Free) => return Err(Error::Stale(index)),
+                    Some(_) => (),
                 }
             }
 
-            Self { maths, maths_by_index_to_solve, unsolved_value_to_math_index_pairs }
+            Ok(Self::new(recipe, values))
         }
 
         pub fn math(&self, index: usize) -> Option<&Whence<rules::Math>> {
@@ -782,17 +1253,51 @@ pub mod solve {
             self.unsolved_value_to_math_index_pairs.as_slice()
         }
 
+        /// Values that are a percentage of nothing (zero over zero) and no other math solved,
+        /// paired with the index of the math that found that.
+        ///
+        /// These aren't errors, like the baker's percentages in a mix without any flour.
+        pub fn undefined(&self) -> &[(Index, usize)] {
+            self.undefined.as_slice()
+        }
+
+        /// Values that some math found to be infinite and no other math solved, paired with the
+        /// index of the math that found that.
+        ///
+        /// These are errors, like a baker's percentage of zero flour that isn't zero itself.
+        pub fn infinite(&self) -> &[(Index, usize)] {
+            self.infinite.as_slice()
+        }
+
+        /// Indexes of maths that the solved values don't agree with, like when the user gave
+        /// the total weight and the weight of every ingredient and they don't add up.
+        ///
+        /// Empty until `step` returns `None`. Values in these maths that aren't inputs are
+        /// marked `State::Conflicting`.
+        pub fn conflicts(&self) -> &[usize] {
+            self.conflicts.as_slice()
+        }
+
         /// on success, yields the value index, value, math index
-        pub fn step(&mut self, values: &mut Values) -> Option<SolveStep> {
-            let Self { maths, maths_by_index_to_solve, unsolved_value_to_math_index_pairs } = self;
+        ///
+        /// `None` once nothing more can be solved.
+        pub fn step(&mut self, values: &mut Values) -> Result<Option<SolveStep>> {
+            let Self {
+                maths,
+                maths_by_index_to_solve,
+                unsolved_value_to_math_index_pairs,
+                undefined,
+                infinite,
+                conflicts,
+                checked,
+                fallbacks,
+            } = self;
 
             while let Some(math_index) = maths_by_index_to_solve.pop() {
                 let Some(MathToSolve { math, unsolved }) = maths.get(math_index) else {
-                    debug_assert!(
-                        false,
-                        "invalid index {math_index} in maths_by_index_to_solve"
-                    );
-                    continue;
+                    return Err(Error::Inconsistent(
+                        "invalid index in maths_by_index_to_solve",
+                    ));
                 };
 
                 if *unsolved < 1 {
@@ -807,24 +1312,39 @@ pub mod solve {
 
                 let Some(solve_for) = math
                     .indexes()
-                    .find(|&value_index| is_unsolved(values.value(value_index)))
+                    .find(|&value_index| !values.state(value_index).is_solved())
                 else {
-                    debug_assert!(
-                            false,
-                            "no unsolved value found in math {math_index} {math:?} popped from maths_by_index_to_solve"
-                        );
-                    continue;
+                    return Err(Error::Inconsistent(
+                        "no unsolved value found in math popped from maths_by_index_to_solve",
+                    ));
                 };
 
-                let value = math.solve_for(solve_for, &values);
+                /* If this doesn't find a value, we don't try this math again because it's
+                 * popped. Hopefully the value can be solved with some other math later on. */
+                let value = match math.solve_for(solve_for, values) {
+                    Solution::Value(value) => value,
+                    Solution::Undefined => {
+                        undefined.push((solve_for, math_index));
+                        values.set_state(solve_for, State::Undefined(math_index));
+                        continue;
+                    }
+                    Solution::Infinite => {
+                        infinite.push((solve_for, math_index));
+                        values.set_state(solve_for, State::Infinite(math_index));
+                        continue;
+                    }
+                    Solution::Unsolved => continue,
+                };
 
-                if is_unsolved(value) {
-                    /* If this happens, we don't try this math again because it's popped. Hopefully
-                     * the value can be solved with some other math later on. */
-                    continue;
-                }
+                let state = if math_index < *fallbacks {
+                    State::Default(math_index)
+                } else {
+                    State::Derived(math_index)
+                };
 
-                *values.value_mut(solve_for) = value;
+                values.set(solve_for, value, state);
+                undefined.retain(|&(index, _)| index != solve_for);
+                infinite.retain(|&(index, _)| index != solve_for);
 
                 /* since solve_for was solved, remove it from unsolved_value_to_math_index_pairs
                  * and update maths where it occurs, possibly queueing them on to
@@ -839,8 +1359,9 @@ pub mod solve {
                     }
 
                     let Some(unsolved_math) = maths.get_mut(unsolved_math_index) else {
-                        debug_assert!(false, "invalid maths index {unsolved_math_index} somewhere in unsolved_value_to_math_index_pairs");
-                        continue;
+                        return Err(Error::Inconsistent(
+                            "invalid maths index in unsolved_value_to_math_index_pairs",
+                        ));
                     };
 
                     unsolved_math.unsolved -= 1;
@@ -852,13 +1373,47 @@ pub mod solve {
                     unsolved_value_to_math_index_pairs.swap_remove(i);
                 }
 
-                return Some((solve_for, value, math_index));
+                return Ok(Some((solve_for, value, math_index)));
+            }
+
+            if !*checked {
+                *checked = true;
+
+                for (math_index, MathToSolve { math, .. }) in maths.iter().enumerate() {
+                    /* a default that was overridden by something else isn't a conflict */
+                    if math_index < *fallbacks {
+                        continue;
+                    }
+
+                    let solved = math.indexes().all(|i| values.state(i).is_solved());
+
+                    if !solved || math.check(values) {
+                        continue;
+                    }
+
+                    conflicts.push(math_index);
+
+                    for i in math.indexes() {
+                        if values.state(i) != State::Input {
+                            values.set_state(i, State::Conflicting);
+                        }
+                    }
+                }
             }
 
-            None
+            Ok(None)
         }
 
-        pub fn iter<'s>(&'s mut self, values: &'s mut Values) -> Iter<&mut Self, &mut Values> {
+        /// steps until nothing more can be solved
+        pub fn solve(&mut self, values: &mut Values) -> Result<()> {
+            while self.step(values)?.is_some() {}
+            Ok(())
+        }
+
+        pub fn iter<'s>(
+            &'s mut self,
+            values: &'s mut Values,
+        ) -> Iter<&'s mut Self, &'s mut Values> {
             Iter(self, values)
         }
     }
@@ -873,11 +1428,11 @@ pub mod solve {
         S: BorrowMut<Solver>,
         V: BorrowMut<Values>,
     {
-        type Item = SolveStep;
+        type Item = Result<SolveStep>;
 
         fn next(&mut self) -> Option<Self::Item> {
             let Iter(solver, values) = self;
-            solver.borrow_mut().step(values.borrow_mut())
+            solver.borrow_mut().step(values.borrow_mut()).transpose()
         }
     }
 
@@ -893,7 +1448,25 @@ pub mod solve {
     }
 }
 
+/// a dough of just wheat and water, with ids 0 and 1, for the tests of every module
+#[cfg(test)]
+pub(crate) fn wheat_and_water() -> (Recipe, Values, WithMixes, WithMixes) {
+    let mut values = Values::new();
+    let mut recipe = values.minimal_recipe().unwrap();
+
+    let wheat = values.new_item().unwrap();
+    let water = values.new_item().unwrap();
+    recipe.dough.flours.push(Ingredient::new(0, wheat.clone()));
+    recipe
+        .dough
+        .nonflours
+        .push(Ingredient::new(1, water.clone()));
+
+    (recipe, values, wheat, water)
+}
+
 #[cfg(test)]
+#[allow(clippy::inconsistent_digit_grouping)]
 mod tests {
     use super::*;
 
@@ -915,37 +1488,37 @@ mod tests {
 
     #[test]
     fn test_funny_pizza() {
-        let mut values = Values::from(Vec::with_capacity(1024));
+        let mut values = Values::new();
 
-        let total = values.new_item();
-        let flour = values.new_item();
-        let nonflour = values.new_item();
+        let total = values.new_item().unwrap();
+        let flour = values.new_item().unwrap();
+        let nonflour = values.new_item().unwrap();
 
-        let water = values.new_item();
-        let salt = values.new_item();
-        let rye = values.new_item();
-        let wheat = values.new_item();
-        let gluten = values.new_item();
-        let double0 = values.new_item();
+        let water = values.new_item().unwrap();
+        let salt = values.new_item().unwrap();
+        let rye = values.new_item().unwrap();
+        let wheat = values.new_item().unwrap();
+        let gluten = values.new_item().unwrap();
+        let double0 = values.new_item().unwrap();
 
         // dough/recipe total weight
-        *values.value_mut(total.weight) = 0.690;
+        *values.value_mut(total.weight).unwrap() = 0.690;
         // FIXME should be assumed?
-        *values.value_mut(flour.bakers) = 1.0;
-        *values.value_mut(flour.percent_in_mixes) = 0.15;
+        *values.value_mut(flour.bakers).unwrap() = 1.0;
+        *values.value_mut(flour.percent_in_mixes).unwrap() = 0.15;
 
-        *values.value_mut(rye.percent_in_mixes) = 1.0;
-        *values.value_mut(gluten.bakers) = 0.07;
-        *values.value_mut(double0.bakers) = 0.25;
-        *values.value_mut(water.bakers) = 0.75;
-        *values.value_mut(salt.bakers) = 0.028;
+        *values.value_mut(rye.percent_in_mixes).unwrap() = 1.0;
+        *values.value_mut(gluten.bakers).unwrap() = 0.07;
+        *values.value_mut(double0.bakers).unwrap() = 0.25;
+        *values.value_mut(water.bakers).unwrap() = 0.75;
+        *values.value_mut(salt.bakers).unwrap() = 0.028;
 
-        let starter_flour = values.new_mix_item();
-        let starter_water = values.new_mix_item();
-        let starter_rye = values.new_mix_item();
+        let starter_flour = values.new_mix_item().unwrap();
+        let starter_water = values.new_mix_item().unwrap();
+        let starter_rye = values.new_mix_item().unwrap();
 
-        *values.value_mut(starter_flour.percent_of_total) = 0.15;
-        *values.value_mut(starter_water.bakers) = 1.40;
+        *values.value_mut(starter_flour.percent_of_total).unwrap() = 0.15;
+        *values.value_mut(starter_water.bakers).unwrap() = 1.40;
 
         let recipe = rules::Recipe {
             dough: rules::Mix {
@@ -954,31 +1527,31 @@ mod tests {
                 nonflour: nonflour.into(),
                 flours: [&rye, &wheat, &gluten, &double0]
                     .into_iter()
-                    .map(|i| Some(rules::Item::from(i.clone())))
+                    .zip(0..)
+                    .map(|(i, id)| Ingredient::new(id, i.clone()))
                     .collect(),
                 nonflours: [&water, &salt]
                     .into_iter()
-                    .map(|i| Some(rules::Item::from(i.clone())))
+                    .zip(4..)
+                    .map(|(i, id)| Ingredient::new(id, i.clone()))
                     .collect(),
             },
             mixes: vec![rules::Mix {
-                total: values.new_mix_item().into(),
+                total: values.new_mix_item().unwrap().into(),
                 flour: starter_flour.clone().into(),
-                nonflour: values.new_mix_item().into(),
-                flours: vec![Some(starter_rye.clone().into())],
-                nonflours: vec![Some(starter_water.clone().into())],
+                nonflour: values.new_mix_item().unwrap().into(),
+                flours: vec![Ingredient::new(0, starter_rye.clone())],
+                nonflours: vec![Ingredient::new(4, starter_water.clone())],
             }],
         };
 
-        assert_eq!(values.did_overflow(), false);
-
         // dbg!(&recipe);
 
         let mut solver = solve::Solver::new(&recipe, &values);
 
-        while let Some((index, value, math)) = solver.step(&mut values) {
+        while let Some((index, value, math)) = solver.step(&mut values).unwrap() {
             // dbg!((index, value, math));
-            // eprintln!("{}", solver.math(math).unwrap().display(&values));
+            // eprintln!("{}", solver.math(math).unwrap().display(&values, &paths));
             #[allow(dropping_copy_types)]
             drop((index, value, math));
         }
@@ -1009,42 +1582,336 @@ mod tests {
 
     #[test]
     fn test_contradiction() {
-        let mut values = Values::from(Vec::with_capacity(1024));
+        let mut values = Values::new();
 
-        let total = values.new_item();
-        let flour = values.new_item();
-        let nonflour = values.new_item();
+        let total = values.new_item().unwrap();
+        let flour = values.new_item().unwrap();
+        let nonflour = values.new_item().unwrap();
 
-        let wheat = values.new_item();
-        let water = values.new_item();
+        let wheat = values.new_item().unwrap();
+        let water = values.new_item().unwrap();
 
-        *values.value_mut(total.weight) = 1.0;
-        *values.value_mut(flour.bakers) = 1.0;
-        *values.value_mut(water.bakers) = 1.0;
-        *values.value_mut(wheat.weight) = 0.123;
+        *values.value_mut(total.weight).unwrap() = 1.0;
+        *values.value_mut(flour.bakers).unwrap() = 1.0;
+        *values.value_mut(water.bakers).unwrap() = 1.0;
+        *values.value_mut(wheat.weight).unwrap() = 0.123;
 
         let recipe = rules::Recipe {
             dough: rules::Mix {
                 total: total.clone().into(),
                 flour: flour.clone().into(),
                 nonflour: nonflour.into(),
-                flours: vec![Some(wheat.clone().into())],
-                nonflours: vec![Some(water.clone().into())],
+                flours: vec![Ingredient::new(0, wheat.clone())],
+                nonflours: vec![Ingredient::new(1, water.clone())],
             },
             mixes: vec![],
         };
 
         let mut solver = solve::Solver::new(&recipe, &values);
+        #[cfg(debug_assertions)]
+        let paths = Paths::new(&recipe);
 
-        while let Some((index, value, math)) = solver.step(&mut values) {
+        while let Some((index, value, math)) = solver.step(&mut values).unwrap() {
             let math: &Whence<_> = solver.math(math).unwrap();
-            eprintln!("{} {}", math.line(), math.display(&values));
+            #[cfg(debug_assertions)]
+            eprintln!("{} {}", math.line(), math.display(&values, &paths));
             #[allow(dropping_copy_types)]
             drop((index, value, math));
         }
 
-        assert_eq!(values.did_overflow(), false);
         assert_eq!(solver.unsolved_value_to_math_index_pairs().len(), 0);
+
+        assert!(matches!(
+            **solver.math(0).unwrap(),
+            rules::Math::TotalFlourBakers100 { index } if index == flour.bakers.index()
+        ));
+
+        /* the total of 1.0 is all water, so the wheat's weight of 0.123 doesn't add up */
+        assert!(!solver.conflicts().is_empty());
+        assert_eq!(values.state(wheat.weight), State::Input);
+        assert!(values.states().contains(&State::Conflicting));
+    }
+
+    #[test]
+    fn test_states() {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        let total = recipe.dough.total.weight;
+
+        *values.value_mut(total).unwrap() = 1700.0;
+        *values.value_mut(water.bakers).unwrap() = 0.7;
+
+        let mut solver = solve::Solver::new(&recipe, &values);
+        solver.solve(&mut values).unwrap();
+
+        assert!(solver.conflicts().is_empty());
+        assert_eq!(
+            values.inputs().collect::<Vec<_>>(),
+            vec![total.index(), water.bakers.index()]
+        );
+        assert!(matches!(values.state(water.weight), State::Derived(_)));
+        assert!(matches!(
+            values.state(recipe.dough.flour.bakers),
+            State::Default(_)
+        ));
+        assert_eq!(values.state(wheat.weight).code(), 2);
+
+        values.reset();
+
+        assert_eq!(values.value(total), 1700.0);
+        assert_eq!(values.state(water.weight), State::Unsolved);
+        assert!(is_unsolved(values.value(water.weight)));
+
+        values.clear(total).unwrap();
+
+        assert_eq!(
+            values.inputs().collect::<Vec<_>>(),
+            vec![water.bakers.index()]
+        );
+    }
+
+    #[test]
+    fn test_values_limit() {
+        let mut values = Values::with_limit(8);
+
+        assert_eq!(values.new_item().map(|_| ()), Ok(()));
+        assert_eq!(
+            values.new_item().map(|_| ()),
+            Err(Error::Capacity { limit: 8 })
+        );
+        /* nothing is added if it doesn't all fit */
+        assert_eq!(values.len(), 6);
+        assert!(values.new_mix_item().is_err());
+        assert_eq!(values.value_indexes(), Ok([6, 7]));
+        assert_eq!(values.value_mut(8 as Index), Err(Error::InvalidIndex(8)));
+
+        let mut values = Values::new();
+        for _ in 0..MAX_VALUES / 3 {
+            values.new_mix_item().unwrap();
+        }
+        assert_eq!(values.value_index(), Ok(Index::MAX));
+        assert!(values.value_index().is_err());
+    }
+
+    #[test]
+    fn test_ingredient_ids() {
+        let mut values = Values::new();
+        let mut recipe = values.minimal_recipe().unwrap();
+
+        let (wheat, salt, water) = (7, 3, 5);
+
+        let dough_wheat = values.new_item().unwrap();
+        let dough_salt = values.new_item().unwrap();
+        let dough_water = values.new_item().unwrap();
+        recipe
+            .dough
+            .flours
+            .push(Ingredient::new(wheat, dough_wheat.clone()));
+        recipe
+            .dough
+            .nonflours
+            .push(Ingredient::new(salt, dough_salt.clone()));
+        recipe
+            .dough
+            .nonflours
+            .push(Ingredient::new(water, dough_water.clone()));
+
+        /* the levain doesn't have salt and lists the water before it would in the dough */
+        let mut levain = values.minimal_mix().unwrap();
+        let levain_water = values.new_mix_item().unwrap();
+        let levain_wheat = values.new_mix_item().unwrap();
+        levain
+            .nonflours
+            .push(Ingredient::new(water, levain_water.clone()));
+        levain
+            .flours
+            .push(Ingredient::new(wheat, levain_wheat.clone()));
+        let levain_flour = levain.flour.clone();
+        recipe.mixes.push(levain);
+
+        *values.value_mut(dough_wheat.weight).unwrap() = 1000.0;
+        *values.value_mut(dough_water.bakers).unwrap() = 0.7;
+        *values.value_mut(dough_salt.bakers).unwrap() = 0.02;
+        *values
+            .value_mut(levain_flour.in_mix().unwrap().percent_of_total)
+            .unwrap() = 0.2;
+        *values.value_mut(levain_water.bakers).unwrap() = 1.0;
+
+        let mut solver = solve::Solver::new(&recipe, &values);
+        solver.solve(&mut values).unwrap();
+
+        assert_eq!(values.value(levain_wheat.weight), 200.0);
+        assert_eq!(values.value(levain_water.weight), 200.0);
+        assert_eq!(values.value(dough_water.weight_less_mixes), 500.0);
+        assert_eq!(values.value(dough_salt.weight_in_mixes), 0.0);
+
+        recipe.remove_ingredient(salt, &mut values);
+
+        assert_eq!(recipe.dough.nonflours.len(), 1);
+        assert_eq!(recipe.path(dough_salt.weight.index()), None);
+        assert!(recipe.path(dough_water.weight.index()).is_some());
+    }
+
+    #[test]
+    fn test_free_values() {
+        let mut values = Values::with_limit(36);
+        let mut recipe = values.minimal_recipe().unwrap();
+
+        let wheat = values.new_item().unwrap();
+        recipe.dough.flours.push(Ingredient::new(0, wheat.clone()));
+        recipe.mixes.push(values.minimal_mix().unwrap());
+        assert_eq!(values.len(), 3 * 6 + 6 + 3 * 3);
+
+        *values.value_mut(wheat.weight).unwrap() = 500.0;
+        values.pin(wheat.weight).unwrap();
+
+        let levain = recipe.mixes[0].clone();
+        recipe.remove_mix(0, &mut values).unwrap();
+        recipe.remove_ingredient(0, &mut values);
+
+        assert!(recipe.mixes.is_empty());
+        assert_eq!(values.state(wheat.weight), State::Free);
+        assert_eq!(values.value_opt(wheat.weight), None);
+        assert_eq!(
+            values.value_mut(wheat.weight),
+            Err(Error::Stale(wheat.weight.index()))
+        );
+        assert!(!values.is_pinned(wheat.weight));
+        assert_eq!(values.inputs().count(), 0);
+
+        /* like value_mut, these don't take stale or made up indexes */
+        let stale = Err(Error::Stale(wheat.weight.index()));
+        assert_eq!(values.pin(wheat.weight), stale);
+        assert_eq!(values.unpin(wheat.weight), stale);
+        assert_eq!(values.clear(wheat.weight), stale);
+        assert_eq!(values.pin(999 as Index), Err(Error::InvalidIndex(999)));
+        assert!(matches!(
+            recipe.remove_mix(0, &mut values),
+            Err(Error::Argument(_))
+        ));
+
+        /* stale indexes are caught when validating */
+        recipe.mixes.push(levain);
+        assert_eq!(
+            solve::Solver::validated(&recipe, &values).err(),
+            Some(Error::Stale(recipe.mixes[0].total.weight.index()))
+        );
+        recipe.mixes.clear();
+
+        /* after a reset, the 15 free values are reused before growing, then there's room for
+         * 3 more */
+        values.reset();
+        let rye = values.new_item().unwrap();
+        let levain = values.minimal_mix().unwrap();
+        assert_eq!(values.len(), 3 * 6 + 6 + 3 * 3);
+        assert_eq!(values.state(rye.weight), State::Unsolved);
+        assert!(values.new_item().is_err());
+        assert!(values.new_mix_item().is_ok());
+
+        recipe.dough.flours.push(Ingredient::new(1, rye.clone()));
+        recipe.mixes.push(levain);
+        *values.value_mut(recipe.dough.total.weight).unwrap() = 800.0;
+
+        let mut solver = solve::Solver::validated(&recipe, &values).unwrap();
+        solver.solve(&mut values).unwrap();
+        assert_eq!(values.value(rye.weight), 800.0);
+    }
+
+    #[test]
+    fn test_stale_after_growing() {
+        let (mut recipe, mut values, _, water) = crate::wheat_and_water();
+        let len = values.len();
+
+        recipe.remove_ingredient(1, &mut values);
+
+        /* the freed values aren't given out again before a reset, so the old index is stale */
+        let salt = values.new_item().unwrap();
+        let grown = values.len();
+        assert!(grown > len);
+        assert_eq!(
+            values.value_mut(water.weight),
+            Err(Error::Stale(water.weight.index()))
+        );
+
+        /* after one, they are */
+        values.reset();
+        let yeast = values.new_item().unwrap();
+        assert_eq!(values.len(), grown);
+        assert_eq!(values.state(yeast.weight), State::Unsolved);
+        assert_eq!(values.state(salt.weight), State::Unsolved);
+    }
+
+    #[test]
+    fn test_zero_flour() {
+        let mut values = Values::new();
+        let mut recipe = values.minimal_recipe().unwrap();
+
+        let wheat = values.new_item().unwrap();
+        let rye = values.new_item().unwrap();
+        let water = values.new_item().unwrap();
+        recipe.dough.flours.push(Ingredient::new(0, wheat.clone()));
+        recipe.dough.flours.push(Ingredient::new(1, rye.clone()));
+        recipe
+            .dough
+            .nonflours
+            .push(Ingredient::new(2, water.clone()));
+
+        *values.value_mut(wheat.weight).unwrap() = 0.0;
+        *values.value_mut(rye.weight).unwrap() = 0.0;
+        *values.value_mut(water.weight).unwrap() = 0.1;
+
+        let mut solver = solve::Solver::new(&recipe, &values);
+        solver.solve(&mut values).unwrap();
+
+        let undefined = solver
+            .undefined()
+            .iter()
+            .map(|&(i, _)| i)
+            .collect::<Vec<_>>();
+        let infinite = solver
+            .infinite()
+            .iter()
+            .map(|&(i, _)| i)
+            .collect::<Vec<_>>();
+
+        /* zero flour is zero percent of zero flour, or any other percentage really */
+        assert!(undefined.contains(&wheat.bakers.index()));
+        assert!(undefined.contains(&rye.bakers.index()));
+        assert!(is_unsolved(values.value(wheat.bakers)));
+
+        /* any water is infinitely many percent of zero flour */
+        assert!(infinite.contains(&water.bakers.index()));
+        assert!(is_unsolved(values.value(water.bakers)));
+
+        for &(index, math) in solver.infinite() {
+            assert_eq!(values.state(index), State::Infinite(math));
+            let math = solver.math(math).unwrap();
+            assert!(math.indexes().any(|i| i == index));
+        }
+
+        /* total flour's percentage is also zero over zero, but that falls back to 100% */
+        assert!(!undefined.contains(&recipe.dough.flour.bakers.index()));
+        assert_eq!(values.value(recipe.dough.flour.bakers), 1.0);
+
+        assert_eq!(round_g(values.value(recipe.dough.total.weight)), 0.1);
+    }
+
+    #[test]
+    fn test_infinite_solved_later() {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        *values.value_mut(wheat.weight).unwrap() = 0.0;
+        *values.value_mut(water.weight).unwrap() = 0.1;
+        *values.value_mut(recipe.dough.total.bakers).unwrap() = 1.7;
+
+        let mut solver = solve::Solver::new(&recipe, &values);
+        solver.solve(&mut values).unwrap();
+
+        /* the water is infinitely many percent of zero flour, until the total's percentage
+         * less the flour's gives it one */
+        assert_eq!(round_pct(values.value(water.bakers)), 0.7);
+        assert!(values.state(water.bakers).is_solved());
+        assert!(solver.infinite().is_empty());
     }
 
     /*
@@ -0,0 +1,394 @@
+//! Solving as a message in and a message out, to solve off the UI thread in a web worker or on a
+//! server.
+//!
+//! `solve` is a pure function from a `Request` to a `Response`. Both encode to bytes that
+//! `postMessage` can transfer instead of copying, a byte for which it is and the version, then the
+//! fields in the order they're declared. The request's document is encoded like in share links,
+//! see `share`, and the response's values are little endian `f32`s so they're exact.
+
+use super::document::Document;
+use super::share::{Limits, Reader, ShareError, Writer};
+use super::solve::Solver;
+use super::{Index, Result, State, Value};
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// the version of the encoding
+pub const VERSION: u8 = 1;
+
+const REQUEST: u8 = b'Q';
+const RESPONSE: u8 = b'R';
+
+/// the bits of `Options`
+const STEPS: u8 = 1 << 0;
+
+/// `Math::kind` by its number in the encoding
+const KINDS: [&str; 3] = ["sum", "percent_of", "total_flour_bakers_100"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    /// copied to the response, to match them up
+    pub id: u32,
+    pub document: Document,
+    pub options: Options,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// fill in `Diagnostics::steps`
+    pub steps: bool,
+}
+
+/// What solving found, values are by their index in the recipe `Document::build` makes from the
+/// request's document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Response {
+    pub id: u32,
+    /// why the document couldn't be solved, everything else is empty if so
+    pub error: Option<String>,
+    /// every value, NaN if it's unsolved
+    pub values: Vec<Value>,
+    /// the `State::code` of every value
+    pub states: Vec<u8>,
+    pub diagnostics: Diagnostics,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics {
+    /// each value in the order it was solved, only with `Options::steps`
+    pub steps: Vec<Step>,
+    /// values in maths that no math solved, in order
+    pub unsolved: Vec<Index>,
+    /// see `Solver::undefined`
+    pub undefined: Vec<Index>,
+    /// see `Solver::infinite`
+    pub infinite: Vec<Index>,
+    /// the indexes in each math that the values don't agree with
+    pub conflicts: Vec<Vec<Index>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub index: Index,
+    pub value: Value,
+    /// the `Math::kind` of the math that solved it
+    pub kind: &'static str,
+}
+
+/// builds and solves the request's document, failing in `Response::error`
+pub fn solve(request: &Request) -> Response {
+    let mut response = Response { id: request.id, ..Default::default() };
+
+    if let Err(e) = solve_into(request, &mut response) {
+        response = Response { id: request.id, error: Some(e.to_string()), ..Default::default() };
+    }
+
+    response
+}
+
+fn solve_into(request: &Request, response: &mut Response) -> Result<()> {
+    let (recipe, mut values) = request.document.build()?;
+    let mut solver = Solver::validated(&recipe, &values)?;
+    let diagnostics = &mut response.diagnostics;
+
+    while let Some((index, value, math)) = solver.step(&mut values)? {
+        if let (true, Some(math)) = (request.options.steps, solver.math(math)) {
+            diagnostics
+                .steps
+                .push(Step { index, value, kind: math.kind() });
+        }
+    }
+
+    let first = |pairs: &[(Index, usize)]| pairs.iter().map(|&(i, _)| i).collect::<Vec<_>>();
+
+    diagnostics.unsolved = first(solver.unsolved_value_to_math_index_pairs());
+    diagnostics.unsolved.sort_unstable();
+    diagnostics.unsolved.dedup();
+    diagnostics.undefined = first(solver.undefined());
+    diagnostics.infinite = first(solver.infinite());
+    diagnostics.conflicts = solver
+        .conflicts()
+        .iter()
+        .filter_map(|&math| solver.math(math))
+        .map(|math| math.indexes().collect())
+        .collect();
+
+    response.values = values.as_slice().to_vec();
+    response.states = values.states().iter().map(|s| s.code()).collect();
+
+    Ok(())
+}
+
+impl Request {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer(vec![REQUEST, VERSION]);
+        w.varint(self.id as u64);
+        w.document(&self.document);
+        w.0.push(if self.options.steps { STEPS } else { 0 });
+        w.0
+    }
+
+    /// the document is checked against `limits` like a share link, `solve` checks the rest
+    pub fn decode(bytes: &[u8], limits: &Limits) -> Result<Self, ShareError> {
+        let mut r = Reader { bytes, limits };
+        header(&mut r, REQUEST)?;
+
+        let id = r.u32()?;
+        let document = r.document()?;
+        let options = match r.byte()? {
+            flags if flags & !STEPS == 0 => Options { steps: flags & STEPS != 0 },
+            _ => return Err(ShareError::Malformed("unknown options")),
+        };
+
+        end(&r)?;
+        Ok(Request { id, document, options })
+    }
+}
+
+impl Response {
+    pub fn encode(&self) -> Vec<u8> {
+        let Response { id, error, values, states, diagnostics } = self;
+        let Diagnostics { steps, unsolved, undefined, infinite, conflicts } = diagnostics;
+
+        let mut w = Writer(vec![RESPONSE, VERSION]);
+        w.varint(*id as u64);
+        w.option(error, |w, error| w.string(error));
+
+        w.varint(values.len() as u64);
+        for v in values {
+            w.0.extend(v.to_le_bytes());
+        }
+
+        w.varint(states.len() as u64);
+        w.0.extend(states);
+
+        w.varint(steps.len() as u64);
+        for Step { index, value, kind } in steps {
+            w.varint(*index as u64);
+            w.0.extend(value.to_le_bytes());
+            /* a kind that isn't in KINDS is out of range and fails to decode */
+            let kind = KINDS.iter().position(|k| k == kind);
+            w.0.push(kind.unwrap_or(KINDS.len()) as u8);
+        }
+
+        let indexes = |w: &mut Writer, list: &[Index]| {
+            w.varint(list.len() as u64);
+            for &i in list {
+                w.varint(i as u64);
+            }
+        };
+
+        indexes(&mut w, unsolved);
+        indexes(&mut w, undefined);
+        indexes(&mut w, infinite);
+
+        w.varint(conflicts.len() as u64);
+        for list in conflicts {
+            indexes(&mut w, list);
+        }
+
+        w.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ShareError> {
+        let limits = Limits::default();
+        let mut r = Reader { bytes, limits: &limits };
+        header(&mut r, RESPONSE)?;
+
+        let id = r.u32()?;
+        let error = r.option(Reader::string)?;
+
+        let count = r.count()?;
+        let values = (0..count)
+            .map(|_| value(&mut r))
+            .collect::<Result<_, _>>()?;
+
+        let count = r.count()?;
+        let states = (0..count)
+            .map(|_| match r.byte()? {
+                code if code < State::CODES => Ok(code),
+                _ => Err(ShareError::Malformed("unknown state")),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let count = r.count()?;
+        let mut steps = Vec::with_capacity(count);
+        for _ in 0..count {
+            let index = index(&mut r)?;
+            let value = value(&mut r)?;
+            let kind = KINDS
+                .get(r.byte()? as usize)
+                .ok_or(ShareError::Malformed("unknown math kind"))?;
+            steps.push(Step { index, value, kind });
+        }
+
+        let unsolved = indexes(&mut r)?;
+        let undefined = indexes(&mut r)?;
+        let infinite = indexes(&mut r)?;
+
+        let count = r.count()?;
+        let conflicts = (0..count)
+            .map(|_| indexes(&mut r))
+            .collect::<Result<_, _>>()?;
+
+        end(&r)?;
+
+        let diagnostics = Diagnostics { steps, unsolved, undefined, infinite, conflicts };
+        Ok(Response { id, error, values, states, diagnostics })
+    }
+}
+
+fn header(r: &mut Reader, kind: u8) -> Result<(), ShareError> {
+    if r.byte()? != kind {
+        return Err(ShareError::Malformed("not that kind of message"));
+    }
+    match r.byte()? {
+        VERSION => Ok(()),
+        v => Err(ShareError::UnknownVersion(v)),
+    }
+}
+
+fn end(r: &Reader) -> Result<(), ShareError> {
+    match r.bytes.is_empty() {
+        true => Ok(()),
+        false => Err(ShareError::Malformed("bytes after the message")),
+    }
+}
+
+fn value(r: &mut Reader) -> Result<Value, ShareError> {
+    let (bytes, rest) = r
+        .bytes
+        .split_first_chunk::<4>()
+        .ok_or(ShareError::Truncated)?;
+    r.bytes = rest;
+    Ok(Value::from_le_bytes(*bytes))
+}
+
+fn index(r: &mut Reader) -> Result<Index, ShareError> {
+    Index::try_from(r.varint()?).map_err(|_| ShareError::Malformed("index too big"))
+}
+
+fn indexes(r: &mut Reader) -> Result<Vec<Index>, ShareError> {
+    let count = r.count()?;
+    (0..count).map(|_| index(r)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{Ingredient, Inputs, Mix, MixIngredient};
+    use crate::{Field, MixPath, Path, Slot};
+
+    fn request() -> Request {
+        let (wheat, water) = (0, 1);
+
+        let document = Document {
+            ingredients: vec![
+                Ingredient::new(wheat, "wheat", true),
+                Ingredient {
+                    inputs: Inputs::bakers(0.7),
+                    ..Ingredient::new(water, "water", false)
+                },
+            ],
+            flour: Inputs::weight(500.0),
+            mixes: vec![Mix {
+                name: "poolish".into(),
+                ingredients: vec![
+                    MixIngredient { id: wheat, inputs: Inputs::in_other(0.2) },
+                    MixIngredient { id: water, inputs: Inputs::bakers(1.0) },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        Request { id: 7, document, options: Options { steps: true } }
+    }
+
+    #[test]
+    fn test_solve() {
+        let request = request();
+        let response = solve(&request);
+
+        assert_eq!(response.id, 7);
+        assert_eq!(response.error, None);
+
+        let (recipe, values) = request.document.solve().unwrap();
+        assert_eq!(response.values.len(), values.len());
+        let total = recipe
+            .index(Path::new(MixPath::Dough, Slot::Total, Field::Weight))
+            .unwrap();
+        assert_eq!(response.values[total as usize].round(), 850.0);
+        assert_eq!(response.states[total as usize], State::Derived(0).code());
+
+        let steps = &response.diagnostics.steps;
+        assert!(steps.iter().any(|step| step.index == total));
+        assert!(steps.iter().all(|step| KINDS.contains(&step.kind)));
+
+        let without = solve(&Request { options: Options::default(), ..request.clone() });
+        assert!(without.diagnostics.steps.is_empty());
+        assert_eq!(without.values.len(), response.values.len());
+
+        let mut bad = request.clone();
+        bad.document.mixes[0].ingredients[0].id = 9;
+        let response = solve(&bad);
+        assert!(response.error.is_some());
+        assert!(response.values.is_empty());
+    }
+
+    #[test]
+    fn test_encode() {
+        let request = request();
+        let bytes = request.encode();
+        assert_eq!(
+            Request::decode(&bytes, &Limits::default()),
+            Ok(request.clone())
+        );
+
+        let mut response = solve(&request);
+        response.diagnostics.conflicts.push(vec![1, 2, 3]);
+        response.diagnostics.infinite.push(4);
+        let bytes = response.encode();
+
+        /* NaN isn't equal to itself */
+        let decoded = Response::decode(&bytes).unwrap();
+        assert_eq!(decoded.encode(), bytes);
+        assert_eq!(decoded.diagnostics, response.diagnostics);
+        assert_eq!(decoded.states, response.states);
+
+        let error = Response { id: 1, error: Some("nope".into()), ..Default::default() };
+        assert_eq!(Response::decode(&error.encode()), Ok(error));
+
+        let malformed = Some(ShareError::Malformed("not that kind of message"));
+        assert_eq!(Response::decode(&request.encode()).err(), malformed);
+        assert_eq!(Request::decode(&bytes, &Limits::default()).err(), malformed);
+
+        assert_eq!(
+            Response::decode(&bytes[..bytes.len() - 1]),
+            Err(ShareError::Truncated)
+        );
+        assert_eq!(
+            Response::decode(&[RESPONSE, 9]),
+            Err(ShareError::UnknownVersion(9))
+        );
+        assert_eq!(
+            Response::decode(&[bytes.as_slice(), &[0]].concat()),
+            Err(ShareError::Malformed("bytes after the message"))
+        );
+
+        let mut unknown = response.clone();
+        unknown.states[0] = State::CODES;
+        assert_eq!(
+            Response::decode(&unknown.encode()),
+            Err(ShareError::Malformed("unknown state"))
+        );
+
+        let limits = Limits { mixes: 0, ..Limits::default() };
+        assert_eq!(
+            Request::decode(&request.encode(), &limits),
+            Err(ShareError::TooManyMixes { count: 1, limit: 0 })
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Where a value index is in a recipe, like `dough.flours[1].bakers`.
+
+use super::rules::{Ingredient, IngredientId, Item, Mix, Recipe};
+use super::{Index, Values};
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MixPath {
+    Dough,
+    /// position in `Recipe::mixes`
+    Mix(usize),
+}
+
+/// An item in a mix, ingredients are by id rather than position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Total,
+    Flour,
+    NonFlour,
+    Flours(IngredientId),
+    NonFlours(IngredientId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Weight,
+    Bakers,
+    /// only in the dough
+    WeightInMixes,
+    WeightLessMixes,
+    PercentInMixes,
+    PercentLessMixes,
+    /// only in mixes
+    PercentOfTotal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Path {
+    pub mix: MixPath,
+    pub slot: Slot,
+    pub field: Field,
+}
+
+impl Path {
+    pub fn new(mix: MixPath, slot: Slot, field: Field) -> Self {
+        Path { mix, slot, field }
+    }
+}
+
+impl fmt::Display for MixPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MixPath::Dough => write!(f, "dough"),
+            MixPath::Mix(i) => write!(f, "mixes[{i}]"),
+        }
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Slot::Total => write!(f, "total"),
+            Slot::Flour => write!(f, "flour"),
+            Slot::NonFlour => write!(f, "nonflour"),
+            Slot::Flours(id) => write!(f, "flours[{id}]"),
+            Slot::NonFlours(id) => write!(f, "nonflours[{id}]"),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self.field {
+            Field::Weight => "weight",
+            Field::Bakers => "bakers",
+            Field::WeightInMixes => "weight_in_mixes",
+            Field::WeightLessMixes => "weight_less_mixes",
+            Field::PercentInMixes => "percent_in_mixes",
+            Field::PercentLessMixes => "percent_less_mixes",
+            Field::PercentOfTotal => "percent_of_total",
+        };
+
+        write!(f, "{}.{}.{field}", self.mix, self.slot)
+    }
+}
+
+impl Mix {
+    pub fn item(&self, slot: Slot) -> Option<&Item> {
+        match slot {
+            Slot::Total => Some(&self.total),
+            Slot::Flour => Some(&self.flour),
+            Slot::NonFlour => Some(&self.nonflour),
+            Slot::Flours(id) => find(&self.flours, id),
+            Slot::NonFlours(id) => find(&self.nonflours, id),
+        }
+    }
+
+    /// every item in the mix, ingredients in the order they're listed
+    pub fn slots(&self) -> impl Iterator<Item = (Slot, &Item)> + '_ {
+        let flours = self.flours.iter().map(|i| (Slot::Flours(i.id), &i.item));
+        let nonflours = self
+            .nonflours
+            .iter()
+            .map(|i| (Slot::NonFlours(i.id), &i.item));
+
+        [
+            (Slot::Total, &self.total),
+            (Slot::Flour, &self.flour),
+            (Slot::NonFlour, &self.nonflour),
+        ]
+        .into_iter()
+        .chain(flours)
+        .chain(nonflours)
+    }
+}
+
+fn find(ingredients: &[Ingredient], id: IngredientId) -> Option<&Item> {
+    ingredients
+        .iter()
+        .find(|ingredient| ingredient.id == id)
+        .map(|ingredient| &ingredient.item)
+}
+
+impl Item {
+    /// `None` if this kind of item doesn't have that field
+    pub fn field(&self, field: Field) -> Option<Index> {
+        self.fields()
+            .find(|&(f, _)| f == field)
+            .map(|(_, index)| index)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (Field, Index)> + '_ {
+        let common = [
+            (Field::Weight, self.weight.into()),
+            (Field::Bakers, self.bakers.into()),
+        ];
+
+        let with_mixes = self.with_mixes().into_iter().flat_map(|item| {
+            [
+                (Field::WeightInMixes, item.weight_in_mixes.into()),
+                (Field::WeightLessMixes, item.weight_less_mixes.into()),
+                (Field::PercentInMixes, item.percent_in_mixes.into()),
+                (Field::PercentLessMixes, item.percent_less_mixes.into()),
+            ]
+        });
+
+        let in_mix = self
+            .in_mix()
+            .map(|item| (Field::PercentOfTotal, item.percent_of_total.into()));
+
+        common.into_iter().chain(with_mixes).chain(in_mix)
+    }
+}
+
+impl Recipe {
+    pub fn mix(&self, mix: MixPath) -> Option<&Mix> {
+        match mix {
+            MixPath::Dough => Some(&self.dough),
+            MixPath::Mix(i) => self.mixes.get(i),
+        }
+    }
+
+    pub fn index(&self, path: Path) -> Option<Index> {
+        self.mix(path.mix)?.item(path.slot)?.field(path.field)
+    }
+
+    /// searches the whole recipe, see `Paths` for looking up many indexes
+    pub fn path(&self, index: Index) -> Option<Path> {
+        self.paths()
+            .find(|&(_, i)| i == index)
+            .map(|(path, _)| path)
+    }
+
+    /// every value index in the recipe with its path, the dough first then each mix in order
+    pub fn paths(&self) -> impl Iterator<Item = (Path, Index)> + '_ {
+        let mixes = self.mixes.iter().enumerate();
+
+        core::iter::once((MixPath::Dough, &self.dough))
+            .chain(mixes.map(|(i, mix)| (MixPath::Mix(i), mix)))
+            .flat_map(|(mix_path, mix)| {
+                mix.slots().flat_map(move |(slot, item)| {
+                    item.fields()
+                        .map(move |(field, index)| (Path::new(mix_path, slot, field), index))
+                })
+            })
+    }
+}
+
+/// Paths by value index, for looking up lots of them like when printing every math.
+#[derive(Debug, Clone, Default)]
+pub struct Paths(Vec<Option<Path>>);
+
+impl Paths {
+    pub fn new(recipe: &Recipe) -> Self {
+        let mut paths = Vec::new();
+
+        for (path, index) in recipe.paths() {
+            let index = index as usize;
+            if paths.len() <= index {
+                paths.resize(index + 1, None);
+            }
+            paths[index] = Some(path);
+        }
+
+        Paths(paths)
+    }
+
+    pub fn get(&self, index: Index) -> Option<Path> {
+        self.0.get(index as usize).cloned().flatten()
+    }
+
+    /// the path if there is one, otherwise the index in brackets
+    pub fn display(&self, index: Index) -> impl fmt::Display {
+        match self.get(index) {
+            Some(path) => path.to_string(),
+            None => format!("[{index}]"),
+        }
+    }
+
+    /// `display` followed by the value
+    pub fn display_value(&self, index: Index, values: &Values) -> impl fmt::Display {
+        format!("{}={}", self.display(index), values.value(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        let mut values = Values::new();
+        let mut recipe = values.minimal_recipe().unwrap();
+
+        let wheat = values.new_item().unwrap();
+        let rye = values.new_item().unwrap();
+        recipe.dough.flours.push(Ingredient::new(2, rye.clone()));
+        recipe.dough.flours.push(Ingredient::new(1, wheat.clone()));
+
+        let mut levain = values.minimal_mix().unwrap();
+        let levain_rye = values.new_mix_item().unwrap();
+        levain.flours.push(Ingredient::new(2, levain_rye.clone()));
+        recipe.mixes.push(levain);
+
+        let path = Path::new(MixPath::Dough, Slot::Flours(1), Field::Bakers);
+        assert_eq!(path.to_string(), "dough.flours[1].bakers");
+        assert_eq!(recipe.index(path), Some(wheat.bakers.index()));
+        assert_eq!(recipe.path(wheat.bakers.index()), Some(path));
+
+        let path = Path::new(MixPath::Mix(0), Slot::Flours(2), Field::PercentOfTotal);
+        assert_eq!(path.to_string(), "mixes[0].flours[2].percent_of_total");
+        assert_eq!(
+            recipe.index(path),
+            Some(levain_rye.percent_of_total.index())
+        );
+
+        /* ingredients that aren't there and fields an item doesn't have */
+        assert_eq!(
+            recipe.index(Path::new(MixPath::Mix(0), Slot::Flours(1), Field::Weight)),
+            None
+        );
+        assert_eq!(
+            recipe.index(Path::new(
+                MixPath::Mix(0),
+                Slot::Total,
+                Field::WeightInMixes
+            )),
+            None
+        );
+
+        /* every value in the recipe has exactly one path */
+        let paths = Paths::new(&recipe);
+        assert_eq!(recipe.paths().count(), values.len());
+        for (path, index) in recipe.paths() {
+            assert_eq!(paths.get(index), Some(path));
+            assert_eq!(recipe.index(path), Some(index));
+        }
+
+        assert_eq!(
+            paths.display(rye.weight_less_mixes.index()).to_string(),
+            "dough.flours[2].weight_less_mixes"
+        );
+        assert_eq!(paths.display(Index::MAX).to_string(), "[65535]");
+    }
+}
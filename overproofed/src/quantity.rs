@@ -0,0 +1,140 @@
+//! Indexes and values that know what they measure.
+//!
+//! `Values` stores plain `Value`s at plain `Index`es. The recipe model holds `Idx<Weight>` and
+//! `Idx<Fraction>` instead so that `rules` can't build a math that, say, takes a percentage of
+//! a percentage.
+
+use super::{Index, Value};
+
+use core::marker::PhantomData;
+
+/// What a value measures.
+pub trait Quantity: Copy {
+    /// for `Debug` on indexes
+    const UNIT: &'static str;
+
+    fn from_value(value: Value) -> Self;
+
+    fn value(self) -> Value;
+}
+
+/// A weight, in grams.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Weight(pub Value);
+
+/// A fraction, like a baker's percentage; 0.7 is 70%.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Fraction(pub Value);
+
+impl Quantity for Weight {
+    const UNIT: &'static str = "g";
+
+    fn from_value(value: Value) -> Self {
+        Weight(value)
+    }
+
+    fn value(self) -> Value {
+        self.0
+    }
+}
+
+impl Quantity for Fraction {
+    const UNIT: &'static str = "%";
+
+    fn from_value(value: Value) -> Self {
+        Fraction(value)
+    }
+
+    fn value(self) -> Value {
+        self.0
+    }
+}
+
+/// uses the formatter's precision, so `format!("{:.0}", Weight(499.6))` is "500g"
+impl core::fmt::Display for Weight {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*}g", precision, self.0),
+            None => write!(f, "{}g", self.0),
+        }
+    }
+}
+
+/// as a percentage, so `format!("{:.1}", Fraction(0.705))` is "70.5%"
+impl core::fmt::Display for Fraction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let percent = self.0 * 100.0;
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*}%", precision, percent),
+            None => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// The index of a value that measures `K`.
+pub struct Idx<K> {
+    index: Index,
+    kind: PhantomData<K>,
+}
+
+pub type WeightIdx = Idx<Weight>;
+pub type FractionIdx = Idx<Fraction>;
+
+impl<K> Idx<K> {
+    pub const fn new(index: Index) -> Self {
+        Idx { index, kind: PhantomData }
+    }
+
+    pub const fn index(self) -> Index {
+        self.index
+    }
+}
+
+impl<K> From<Idx<K>> for Index {
+    fn from(idx: Idx<K>) -> Index {
+        idx.index
+    }
+}
+
+/* not derived, those would only be implemented where K implements them too */
+
+impl<K> Clone for Idx<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Idx<K> {}
+
+impl<K> PartialEq for Idx<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<K> Eq for Idx<K> {}
+
+impl<K> core::hash::Hash for Idx<K> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<K: Quantity> core::fmt::Debug for Idx<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}[{}]", K::UNIT, self.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(format!("{}", Weight(500.0)), "500g");
+        assert_eq!(format!("{:.0}", Weight(499.6)), "500g");
+        assert_eq!(format!("{:.1}", Fraction(0.705)), "70.5%");
+        assert_eq!(format!("{:?}", FractionIdx::new(3)), "%[3]");
+    }
+}
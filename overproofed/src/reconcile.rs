@@ -0,0 +1,409 @@
+//! Least-squares reconciliation of inputs that don't quite agree, like rounded numbers copied
+//! out of a book.
+
+use super::rules::{self, Math, Recipe};
+use super::solve::Solver;
+use super::{Error, Index, Result, State, Value, Values};
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// An input the reconciled value should stay close to.
+///
+/// The cost of moving it is `weight * (value - target)²`, so a target with a bigger weight moves
+/// less.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub index: Index,
+    pub value: Value,
+    pub weight: Value,
+}
+
+impl Target {
+    pub fn new(index: impl Into<Index>, value: Value) -> Self {
+        Target { index: index.into(), value, weight: 1.0 }
+    }
+
+    /// weighted by one over the value squared, so deviations are compared as a fraction of the
+    /// target; being 1g off of 1000g costs the same as 0.01% off of 1%
+    pub fn relative(index: impl Into<Index>, value: Value) -> Self {
+        let weight = if value == 0.0 {
+            1.0
+        } else {
+            1.0 / (value * value)
+        };
+        Target { index: index.into(), value, weight }
+    }
+
+    pub fn weight(self, weight: Value) -> Self {
+        Target { weight, ..self }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub max_iterations: usize,
+    /// stop once no residual or step is bigger than this, relative to the biggest value
+    pub tolerance: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { max_iterations: 32, tolerance: 1e-9 }
+    }
+}
+
+/// How far a target had to move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    pub index: Index,
+    pub target: Value,
+    pub value: Value,
+}
+
+impl Adjustment {
+    pub fn delta(&self) -> Value {
+        self.value - self.target
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reconciled {
+    /// one for each target, in the same order
+    pub adjustments: Vec<Adjustment>,
+    pub iterations: usize,
+    /// false if `Options::max_iterations` was reached first
+    pub converged: bool,
+}
+
+pub fn reconcile(recipe: &Recipe, values: &mut Values, targets: &[Target]) -> Result<Reconciled> {
+    reconcile_with(recipe, values, targets, Options::default())
+}
+
+/// Writes the targets into `values`, solves, then moves the solved values until every math that
+/// could be solved is consistent.
+///
+/// Targets move according to their weights, values that aren't targets are free to move as much
+/// as they need to. Values that couldn't be solved are left unsolved.
+///
+/// Fails with `Error::Argument` if a target's value isn't finite or its weight isn't positive.
+pub fn reconcile_with(
+    recipe: &Recipe,
+    values: &mut Values,
+    targets: &[Target],
+    options: Options,
+) -> Result<Reconciled> {
+    /* a weight that isn't positive makes the system indefinite, it'd find a saddle point */
+    for target in targets {
+        if !target.value.is_finite() {
+            return Err(Error::Argument("target value isn't finite"));
+        }
+        if !(target.weight > 0.0 && target.weight.is_finite()) {
+            return Err(Error::Argument("target weight isn't positive"));
+        }
+    }
+
+    for &Target { index, value, .. } in targets {
+        *values.value_mut(index)? = value;
+    }
+
+    Solver::new(recipe, values).solve(values)?;
+
+    /* only maths where everything is solved constrain anything, the rest have at least one free
+     * value that can take up whatever slack there is */
+    let maths = rules::for_recipe(recipe)
+        .map(|math| math.into_inner())
+        .filter(|math| math.indexes().all(|i| values.state(i).is_solved()))
+        .collect::<Vec<Math>>();
+
+    let mut variables = maths.iter().flat_map(Math::indexes).collect::<Vec<Index>>();
+    variables.sort_unstable();
+    variables.dedup();
+
+    let var = |index: Index| variables.binary_search(&index).ok();
+
+    let mut x = variables
+        .iter()
+        .map(|&i| values.value(i) as f64)
+        .collect::<Vec<f64>>();
+
+    /* the inverse of each variable's weight, and its target if it has one */
+    let mut inverse_weights = vec![f64::NAN; variables.len()];
+    let mut target_values = vec![None; variables.len()];
+
+    for target in targets {
+        if let Some(v) = var(target.index) {
+            inverse_weights[v] = 1.0 / (target.weight as f64);
+            target_values[v] = Some(target.value as f64);
+        }
+    }
+
+    /* free variables get a tiny weight instead of none so that the step is still unique, the
+     * smallest change that fixes the residuals */
+    let free = FREE
+        * inverse_weights
+            .iter()
+            .cloned()
+            .filter(|w| w.is_finite())
+            .fold(1.0, f64::max);
+
+    inverse_weights
+        .iter_mut()
+        .filter(|w| !w.is_finite())
+        .for_each(|w| *w = free);
+
+    let (n, m) = (variables.len(), maths.len());
+    let mut jacobian = vec![0.0; m * n];
+    let mut normal = vec![0.0; m * m];
+    let mut rhs = vec![0.0; m];
+    let mut offsets = vec![0.0; n];
+
+    let mut iterations = 0;
+    let mut converged = false;
+
+    while iterations < options.max_iterations {
+        iterations += 1;
+
+        let value = |i: Index| var(i).map(|v| x[v]).unwrap_or(f64::NAN);
+
+        /* distance from each target, zero for free variables */
+        for (v, offset) in offsets.iter_mut().enumerate() {
+            *offset = target_values[v].map(|t| x[v] - t).unwrap_or(0.0);
+        }
+
+        jacobian.iter_mut().for_each(|j| *j = 0.0);
+
+        let mut worst_residual: f64 = 0.0;
+
+        for (k, math) in maths.iter().enumerate() {
+            let row = &mut jacobian[k * n..][..n];
+            math.partials(value, |i, d| {
+                if let Some(v) = var(i) {
+                    row[v] += d;
+                }
+            });
+
+            let residual = math.residual(value);
+            worst_residual = worst_residual.max(residual.abs());
+
+            /* the step has to satisfy residual + row·step = 0 */
+            rhs[k] = residual - row.iter().zip(&offsets).map(|(j, o)| j * o).sum::<f64>();
+        }
+
+        /* minimizing (offset + step)ᵀ W (offset + step) subject to the linearized maths gives
+         * step = -offset - W⁻¹ Jᵀ λ where (J W⁻¹ Jᵀ) λ = rhs */
+        for a in 0..m {
+            for b in a..m {
+                let (ra, rb) = (&jacobian[a * n..][..n], &jacobian[b * n..][..n]);
+                let dot = (0..n)
+                    .filter(|&v| ra[v] != 0.0 && rb[v] != 0.0)
+                    .map(|v| ra[v] * inverse_weights[v] * rb[v])
+                    .sum::<f64>();
+                normal[a * m + b] = dot;
+                normal[b * m + a] = dot;
+            }
+        }
+
+        let lambda = solve_symmetric(&mut normal, m, &mut rhs);
+
+        let mut worst_step: f64 = 0.0;
+
+        for v in 0..n {
+            let jt_lambda = (0..m).map(|k| jacobian[k * n + v] * lambda[k]).sum::<f64>();
+            let step = -offsets[v] - inverse_weights[v] * jt_lambda;
+            worst_step = worst_step.max(step.abs());
+            x[v] += step;
+        }
+
+        let tolerance = options.tolerance * x.iter().fold(1.0, |m, v| v.abs().max(m));
+
+        if worst_residual <= tolerance && worst_step <= tolerance {
+            converged = true;
+            break;
+        }
+    }
+
+    for (&index, &v) in variables.iter().zip(&x) {
+        values.adjust(index, v as Value);
+    }
+
+    /* solving marked the values in maths that didn't add up as conflicting, now that they do,
+     * they get the states solving a copy from the reconciled inputs gives them */
+    if converged && values.states().contains(&State::Conflicting) {
+        let mut solved = values.clone();
+        solved.reset();
+        Solver::new(recipe, &solved).solve(&mut solved)?;
+
+        for (i, &state) in solved.states().iter().enumerate() {
+            if values.state(i as Index) == State::Conflicting {
+                values.set_state(i as Index, state);
+            }
+        }
+    }
+
+    let adjustments = targets
+        .iter()
+        .map(|&Target { index, value: target, .. }| Adjustment {
+            index,
+            target,
+            value: values.value(index),
+        })
+        .collect();
+
+    Ok(Reconciled { adjustments, iterations, converged })
+}
+
+/// how much cheaper it is to move a free value than the cheapest target
+const FREE: f64 = 1e6;
+
+/// Solves `a x = b` for symmetric positive semi-definite `a` (n by n, row major) by gaussian
+/// elimination, reusing `b` for `x`.
+///
+/// Rows that turn out to be linearly dependent on earlier ones, like from redundant maths, are
+/// skipped and get zero in the solution.
+fn solve_symmetric(a: &mut [f64], n: usize, b: &mut [f64]) -> Vec<f64> {
+    let diagonal = (0..n).map(|k| a[k * n + k]).collect::<Vec<f64>>();
+    let mut dependent = vec![false; n];
+
+    for k in 0..n {
+        let pivot = a[k * n + k];
+
+        /* what's left of the row after eliminating the ones above it is tiny compared to what it
+         * started as, so it was (nearly) a combination of those */
+        if pivot <= diagonal[k] * 1e-10 {
+            dependent[k] = true;
+            continue;
+        }
+
+        for i in k + 1..n {
+            let f = a[i * n + k] / pivot;
+            if f == 0.0 {
+                continue;
+            }
+            for j in k..n {
+                a[i * n + j] -= f * a[k * n + j];
+            }
+            b[i] -= f * b[k];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+
+    for k in (0..n).rev() {
+        if dependent[k] {
+            continue;
+        }
+        let rest = (k + 1..n).map(|j| a[k * n + j] * x[j]).sum::<f64>();
+        x[k] = (b[k] - rest) / a[k * n + k];
+    }
+
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_consistent(recipe: &Recipe, values: &Values) {
+        for math in rules::for_recipe(recipe) {
+            let residual = math.residual(|i| values.value(i) as f64);
+            assert!(
+                residual.is_nan() || residual.abs() < 1e-3,
+                "{:?} {}",
+                *math,
+                residual
+            );
+        }
+    }
+
+    #[test]
+    fn test_reconcile_rounded_inputs() {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        /* 1000g at 70% hydration is 588.235g flour and 411.765g water, rounded to the gram */
+        let targets = [
+            Target::relative(recipe.dough.total.weight, 1000.0),
+            Target::relative(water.bakers, 0.70),
+            Target::relative(wheat.weight, 588.0),
+            Target::relative(water.weight, 412.0),
+        ];
+
+        let reconciled = reconcile(&recipe, &mut values, &targets).unwrap();
+
+        assert!(reconciled.converged);
+        assert_consistent(&recipe, &values);
+        assert!(!values.states().contains(&State::Conflicting));
+        assert_eq!(values.state(wheat.weight), State::Input);
+        assert!(matches!(values.state(wheat.bakers), State::Derived(_)));
+
+        for adjustment in &reconciled.adjustments {
+            assert!(
+                (adjustment.delta() / adjustment.target).abs() < 0.002,
+                "{adjustment:?}"
+            );
+        }
+
+        assert_eq!(values.value(wheat.bakers), 1.0);
+        assert_eq!(
+            values.value(recipe.dough.flour.weight),
+            values.value(wheat.weight)
+        );
+    }
+
+    #[test]
+    fn test_reconcile_consistent_inputs_stay_put() {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        let targets = [
+            Target::new(recipe.dough.total.weight, 1700.0),
+            Target::new(water.bakers, 0.70),
+        ];
+
+        let reconciled = reconcile(&recipe, &mut values, &targets).unwrap();
+
+        assert!(reconciled.converged);
+        assert_consistent(&recipe, &values);
+
+        for adjustment in &reconciled.adjustments {
+            assert!(adjustment.delta().abs() < 1e-4, "{adjustment:?}");
+        }
+
+        assert!((values.value(wheat.weight) - 1000.0).abs() < 1e-3);
+        assert!((values.value(water.weight) - 700.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_reconcile_weights() {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        /* 10g too much total, the heavily weighted flour and water shouldn't budge */
+        let targets = [
+            Target::new(recipe.dough.total.weight, 1010.0),
+            Target::new(wheat.weight, 600.0).weight(1e6),
+            Target::new(water.weight, 400.0).weight(1e6),
+        ];
+
+        let reconciled = reconcile(&recipe, &mut values, &targets).unwrap();
+
+        assert!(reconciled.converged);
+        assert_consistent(&recipe, &values);
+
+        let [total, wheat, water] = reconciled.adjustments[..] else {
+            unreachable!()
+        };
+        assert!((total.delta() + 10.0).abs() < 0.01, "{total:?}");
+        assert!(wheat.delta().abs() < 0.01, "{wheat:?}");
+        assert!(water.delta().abs() < 0.01, "{water:?}");
+
+        for target in [
+            Target::new(total.index, 1000.0).weight(0.0),
+            Target::new(total.index, 1000.0).weight(f32::NAN),
+            Target::relative(total.index, f32::INFINITY),
+        ] {
+            assert!(matches!(
+                reconcile(&recipe, &mut values, &[target]),
+                Err(Error::Argument(_))
+            ));
+        }
+    }
+}
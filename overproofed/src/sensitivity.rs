@@ -0,0 +1,166 @@
+//! How much each solved value changes per unit change of each input, like "+1% salt adds 9.6g
+//! of salt and 9.6g to the total".
+
+use super::rules::Recipe;
+use super::solve::Solver;
+use super::{Index, Result, Value, Values};
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub input: Index,
+    pub output: Index,
+    /// change in the output per unit change in the input
+    pub derivative: Value,
+}
+
+/// Sparse input by output matrix, ordered by input then by the order outputs were solved in.
+///
+/// Outputs that don't depend on an input have no entry for it, and neither does an input for
+/// itself; see `derivative`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sensitivity {
+    pub entries: Vec<Entry>,
+}
+
+impl Sensitivity {
+    /// Solves `values` and differentiates every value it solved with respect to every input.
+    ///
+    /// Only the inputs in `values` are kept, anything it solved is solved again.
+    pub fn new(recipe: &Recipe, values: &mut Values) -> Result<Self> {
+        values.reset();
+
+        let inputs = values.inputs().collect::<Vec<_>>();
+
+        let mut solver = Solver::new(recipe, values);
+        let steps = solver
+            .iter(values)
+            .map(|step| step.map(|(index, _, math)| (index, math)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let value = |i: Index| values.value(i) as f64;
+
+        let mut entries = Vec::new();
+        let mut derivatives = vec![0.0f64; values.len()];
+
+        for &input in &inputs {
+            derivatives.iter_mut().for_each(|d| *d = 0.0);
+            derivatives[input as usize] = 1.0;
+
+            for &(output, math) in &steps {
+                let Some(math) = solver.math(math) else {
+                    continue;
+                };
+
+                /* the residual stays zero, so the partial for the output times its derivative
+                 * cancels out the partials of everything else times theirs */
+                let (mut own, mut rest) = (0.0, 0.0);
+                math.partials(value, |i, partial| {
+                    if i == output {
+                        own += partial;
+                    } else {
+                        rest += partial * derivatives[i as usize];
+                    }
+                });
+
+                let derivative = if own == 0.0 { 0.0 } else { -rest / own };
+                derivatives[output as usize] = derivative;
+
+                if derivative != 0.0 {
+                    let derivative = derivative as Value;
+                    entries.push(Entry { input, output, derivative });
+                }
+            }
+        }
+
+        Ok(Sensitivity { entries })
+    }
+
+    /// zero if `output` doesn't depend on `input`, one if they're the same value
+    pub fn derivative(&self, input: impl Into<Index>, output: impl Into<Index>) -> Value {
+        let (input, output) = (input.into(), output.into());
+        if input == output {
+            return 1.0;
+        }
+        self.entries
+            .iter()
+            .find(|e| e.input == input && e.output == output)
+            .map(|e| e.derivative)
+            .unwrap_or(0.0)
+    }
+
+    pub fn for_input(&self, input: impl Into<Index>) -> impl Iterator<Item = &Entry> + '_ {
+        let input = input.into();
+        self.entries.iter().filter(move |e| e.input == input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ingredient;
+
+    #[test]
+    fn test_sensitivity_salt() {
+        let (mut recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        let salt = values.new_item().unwrap();
+        recipe
+            .dough
+            .nonflours
+            .push(Ingredient::new(2, salt.clone()));
+
+        let total = recipe.dough.total.weight;
+
+        *values.value_mut(wheat.weight).unwrap() = 960.0;
+        *values.value_mut(water.bakers).unwrap() = 0.70;
+        *values.value_mut(salt.bakers).unwrap() = 0.02;
+
+        let sensitivity = Sensitivity::new(&recipe, &mut values).unwrap();
+
+        let d = |input: Index, output: Index| {
+            (sensitivity.derivative(input, output) * 1000.0).round() / 1000.0
+        };
+
+        /* one more percent of salt is 9.6g more salt and 9.6g more dough */
+        assert_eq!(d(salt.bakers.into(), salt.weight.into()) / 100.0, 9.6);
+        assert_eq!(d(salt.bakers.into(), total.into()) / 100.0, 9.6);
+        assert_eq!(d(salt.bakers.into(), water.weight.into()), 0.0);
+        assert_eq!(d(salt.bakers.into(), wheat.weight.into()), 0.0);
+
+        assert_eq!(d(wheat.weight.into(), total.into()), 1.72);
+        assert_eq!(d(wheat.weight.into(), water.weight.into()), 0.70);
+        assert_eq!(d(water.bakers.into(), water.weight.into()), 960.0);
+
+        /* an input moves one for one with itself, but isn't an entry */
+        assert_eq!(d(salt.bakers.into(), salt.bakers.into()), 1.0);
+        assert!(sensitivity.entries.iter().all(|e| e.input != e.output));
+
+        assert!(sensitivity
+            .for_input(salt.bakers)
+            .all(|e| e.input == salt.bakers.index()));
+    }
+
+    #[test]
+    fn test_sensitivity_fixed_total() {
+        let (recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        *values.value_mut(recipe.dough.total.weight).unwrap() = 1700.0;
+        *values.value_mut(water.bakers).unwrap() = 0.70;
+
+        let sensitivity = Sensitivity::new(&recipe, &mut values).unwrap();
+
+        /* flour = total / (1 + hydration) so d flour / d hydration = -total / (1 + hydration)² */
+        let d = sensitivity.derivative(water.bakers, wheat.weight);
+        assert!((d - -1700.0 / (1.7 * 1.7)).abs() < 1e-2, "{d}");
+
+        /* and whatever the flour loses, the water gains */
+        let d_water = sensitivity.derivative(water.bakers, water.weight);
+        assert!((d + d_water).abs() < 1e-2, "{d} {d_water}");
+
+        /* the values are solved now, that's no different */
+        assert_eq!(Sensitivity::new(&recipe, &mut values), Ok(sensitivity));
+    }
+}
@@ -0,0 +1,201 @@
+//! `serde` for `Values` and the indexes in the recipe model, the model types derive theirs.
+//! Human-readable formats get each value next to its state, compact ones get two lists.
+
+use super::{is_unsolved, Idx, Index, State, Value, Values, MAX_VALUES, UNSOLVED};
+
+use alloc::vec::Vec;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+impl<K> Serialize for Idx<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.index().serialize(serializer)
+    }
+}
+
+impl<'de, K> Deserialize<'de> for Idx<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Index::deserialize(deserializer).map(Idx::new)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Readable {
+    limit: usize,
+    values: Vec<(Option<Value>, State)>,
+    pinned: Vec<Index>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Compact(usize, Vec<Value>, Vec<State>, Vec<Index>);
+
+impl Serialize for Values {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Values { buf, states, limit, pinned, .. } = self;
+
+        if serializer.is_human_readable() {
+            let values = buf
+                .iter()
+                .map(|&v| Some(v).filter(|&v| !is_unsolved(v)))
+                .zip(states.iter().cloned())
+                .collect();
+            Readable { limit: *limit, values, pinned: pinned.clone() }.serialize(serializer)
+        } else {
+            Compact(*limit, buf.clone(), states.clone(), pinned.clone()).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Values {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (limit, buf, states, pinned) = if deserializer.is_human_readable() {
+            let Readable { limit, values, pinned } = Readable::deserialize(deserializer)?;
+            let (buf, states) = values
+                .into_iter()
+                .map(|(v, state)| (v.unwrap_or(UNSOLVED), state))
+                .unzip();
+            (limit, buf, states, pinned)
+        } else {
+            let Compact(limit, buf, states, pinned) = Compact::deserialize(deserializer)?;
+            (limit, buf, states, pinned)
+        };
+
+        from_parts(limit, buf, states, pinned).map_err(D::Error::custom)
+    }
+}
+
+/// checks what `Values` relies on, the freed values are rebuilt from the states
+fn from_parts(
+    limit: usize,
+    buf: Vec<Value>,
+    states: Vec<State>,
+    pinned: Vec<Index>,
+) -> Result<Values, &'static str> {
+    if buf.len() != states.len() {
+        return Err("not as many states as values");
+    }
+
+    if limit > MAX_VALUES || buf.len() > limit {
+        return Err("more values than the limit");
+    }
+
+    /* the solver takes a solved state to mean there's a number there */
+    if buf
+        .iter()
+        .zip(&states)
+        .any(|(&v, s)| is_unsolved(v) && s.is_solved())
+    {
+        return Err("unsolved value with a solved state");
+    }
+
+    if pinned.windows(2).any(|w| w[0] >= w[1]) {
+        return Err("pinned values aren't sorted");
+    }
+
+    if pinned
+        .iter()
+        .any(|&i| states.get(i as usize).is_none_or(|&s| s == State::Free))
+    {
+        return Err("pinned value that isn't there");
+    }
+
+    /* not reused until a reset, since whatever had them might still be around */
+    let freed = (0..states.len())
+        .filter(|&i| states[i] == State::Free)
+        .map(|i| i as Index)
+        .collect();
+
+    Ok(Values { buf, states, limit, pinned, free: Vec::new(), freed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solve::Solver;
+    use crate::{Ingredient, Recipe};
+
+    fn solved() -> (Recipe, Values) {
+        let (mut recipe, mut values, wheat, water) = crate::wheat_and_water();
+
+        let salt = values.new_item().unwrap();
+        recipe.dough.nonflours.push(Ingredient::new(2, salt));
+        recipe.remove_ingredient(2, &mut values);
+
+        *values.value_mut(wheat.weight).unwrap() = 1000.0;
+        *values.value_mut(water.bakers).unwrap() = 0.7;
+        values.pin(water.bakers).unwrap();
+
+        Solver::new(&recipe, &values).solve(&mut values).unwrap();
+
+        (recipe, values)
+    }
+
+    fn assert_same(a: &Values, b: &Values) {
+        assert_eq!(a.len(), b.len());
+        assert_eq!(a.limit(), b.limit());
+        assert_eq!(a.states(), b.states());
+        assert_eq!(a.pinned(), b.pinned());
+        let free = |v: &Values| {
+            let mut free = [v.free.as_slice(), v.freed.as_slice()].concat();
+            free.sort();
+            free
+        };
+        assert_eq!(free(a), free(b));
+        for i in 0..a.len() as Index {
+            assert_eq!(a.value(i).to_bits(), b.value(i).to_bits());
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (recipe, values) = solved();
+
+        let json = serde_json::to_string(&values).unwrap();
+        assert!(json.starts_with(r#"{"limit":65536,"values":[[1700.0,{"Derived":"#));
+        assert!(json.contains(r#"[null,"Free"]"#));
+        assert_same(&serde_json::from_str(&json).unwrap(), &values);
+
+        let bytes = postcard::to_allocvec(&values).unwrap();
+        assert!(bytes.len() < json.len() / 2);
+        assert_same(&postcard::from_bytes(&bytes).unwrap(), &values);
+
+        /* the recipe comes back with the same indexes and solves the same */
+        let json = serde_json::to_string(&recipe).unwrap();
+        let from_json: Recipe = serde_json::from_str(&json).unwrap();
+        let bytes = postcard::to_allocvec(&recipe).unwrap();
+        let from_postcard: Recipe = postcard::from_bytes(&bytes).unwrap();
+
+        for recipe in [from_json, from_postcard] {
+            let mut values = values.clone();
+            values.reset();
+            Solver::new(&recipe, &values).solve(&mut values).unwrap();
+            assert_same(&values, &solved().1);
+        }
+    }
+
+    #[test]
+    fn test_bad_values() {
+        let bad = [
+            r#"{"limit":1,"values":[[1.0,"Input"],[null,"Unsolved"]],"pinned":[]}"#,
+            r#"{"limit":2,"values":[[1.0,"Input"],[null,"Unsolved"]],"pinned":[1,0]}"#,
+            r#"{"limit":2,"values":[[1.0,"Input"],[null,"Free"]],"pinned":[1]}"#,
+            r#"{"limit":2,"values":[[1.0,"Input"]],"pinned":[3]}"#,
+            r#"{"limit":2,"values":[[null,"Input"]],"pinned":[]}"#,
+            r#"{"limit":2,"values":[[null,{"Derived":0}]],"pinned":[]}"#,
+            r#"{"limit":2,"values":[[null,"Conflicting"]],"pinned":[]}"#,
+        ];
+        for json in bad {
+            assert!(serde_json::from_str::<Values>(json).is_err(), "{json}");
+        }
+
+        let bytes = postcard::to_allocvec(&Compact(2, vec![1.0], vec![], vec![])).unwrap();
+        assert!(postcard::from_bytes::<Values>(&bytes).is_err());
+
+        let nan = Compact(2, vec![UNSOLVED], vec![State::Default(0)], vec![]);
+        let bytes = postcard::to_allocvec(&nan).unwrap();
+        assert!(postcard::from_bytes::<Values>(&bytes).is_err());
+
+        let fine = r#"{"limit":2,"values":[[null,"Free"],[null,{"Undefined":0}]],"pinned":[]}"#;
+        assert!(serde_json::from_str::<Values>(fine).is_ok());
+    }
+}
@@ -0,0 +1,854 @@
+//! Share links, a recipe packed small enough to fit in a URL.
+//!
+//! There are two kinds of link. The web app's first links are its JSON compressed with lz4 then
+//! base64 encoded, `compact` and `expand` do that for any text. Links from `encode` start with
+//! `~` and are a binary encoding of a `Document`, base64 encoded, less than half as long.
+//! `decode` reads either, the JSON ones only with the `json` feature.
+//!
+//! With the `message` feature and not `share`, only the binary encoding is here, for `message`.
+//!
+//! Links come from anywhere, so decoding doesn't trust them. The size lz4 prepends is checked
+//! against `Limits` before anything is allocated for it, and the recipe in it is checked for
+//! how many ingredients and mixes it has before it's built.
+//!
+//! The binary encoding, after its version byte, is the document's fields in the order they're
+//! declared, ending with a CRC-16 of everything before it. Counts and ids are LEB128 varints,
+//! strings are a count of bytes then UTF-8, and an `Option` is a byte, 0 or 1, then maybe the
+//! value. Each `Inputs` is a byte with a bit for each field that's set, followed by those
+//! fields. An ingredient's `is_flour` is another bit in that byte. A number is a varint of
+//! `zigzag(m) << 3 | d` for the number `m / 10^d`, with `d` up to 6, so 0.72 is `72 << 3 | 2`;
+//! `d` of 7 is for numbers that aren't a short decimal and has the number's bits instead of `m`.
+
+use super::document::{Document, Ingredient, Inputs, Meta, Mix, MixIngredient, Yield};
+use super::{Error, Result, Value};
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(feature = "json")]
+use super::format;
+
+/// The most a share link may be or have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// bytes of base64
+    pub encoded: usize,
+    /// bytes of JSON
+    pub decompressed: usize,
+    pub ingredients: usize,
+    pub mixes: usize,
+}
+
+/// a lot more than anyone bakes with, but not enough to freeze a page
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { encoded: 64 * 1024, decompressed: 256 * 1024, ingredients: 256, mixes: 64 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareError {
+    TooLong {
+        len: usize,
+        limit: usize,
+    },
+    Base64,
+    /// ends before it should, or is too short to have the size lz4 prepends
+    Truncated,
+    /// the size lz4 prepends is over the limit
+    TooBig {
+        size: usize,
+        limit: usize,
+    },
+    /// the compressed bytes are bad or don't decompress to the size lz4 prepends
+    Lz4,
+    Utf8,
+    TooManyIngredients {
+        count: usize,
+        limit: usize,
+    },
+    TooManyMixes {
+        count: usize,
+        limit: usize,
+    },
+    /// a binary link with a version newer than `VERSION` maybe
+    UnknownVersion(u8),
+    /// a binary link that was changed or cut short
+    Checksum,
+    /// a binary link that checks out but isn't a document, and why
+    Malformed(&'static str),
+    /// a JSON link without the `json` feature to read it
+    Json,
+}
+
+impl fmt::Display for ShareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareError::TooLong { len, limit } => write!(f, "link is {len} long, over {limit}"),
+            ShareError::Base64 => write!(f, "link isn't base64"),
+            ShareError::Truncated => write!(f, "link is too short"),
+            ShareError::TooBig { size, limit } => {
+                write!(f, "link expands to {size} bytes, over {limit}")
+            }
+            ShareError::Lz4 => write!(f, "link doesn't decompress"),
+            ShareError::Utf8 => write!(f, "link isn't text"),
+            ShareError::TooManyIngredients { count, limit } => {
+                write!(f, "recipe has {count} ingredients, over {limit}")
+            }
+            ShareError::TooManyMixes { count, limit } => {
+                write!(f, "recipe has {count} mixes, over {limit}")
+            }
+            ShareError::UnknownVersion(v) => write!(f, "unknown link version {v}"),
+            ShareError::Checksum => write!(f, "link checksum doesn't match"),
+            ShareError::Malformed(why) => write!(f, "malformed link: {why}"),
+            ShareError::Json => write!(f, "link is JSON which this can't read"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ShareError {}
+
+#[cfg(feature = "share")]
+impl From<ShareError> for Error {
+    fn from(e: ShareError) -> Self {
+        Error::Share(e)
+    }
+}
+
+#[cfg(feature = "share")]
+const SIZE_PREFIX: usize = 4;
+
+/// the version of the binary encoding `encode` writes
+pub const VERSION: u8 = 1;
+
+/// what a binary link starts with, JSON links can't since `~` isn't base64
+pub const PREFIX: char = '~';
+
+#[cfg(feature = "share")]
+/// the inverse of `expand`
+pub fn compact(s: &str) -> String {
+    let smol = lz4_flex::block::compress_prepend_size(s.as_bytes());
+    base64::encode_config(smol, base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(feature = "share")]
+/// the text in a link, without looking at what the text is
+pub fn expand(s: &str, limits: &Limits) -> Result<String, ShareError> {
+    if s.len() > limits.encoded {
+        return Err(ShareError::TooLong { len: s.len(), limit: limits.encoded });
+    }
+
+    let smol =
+        base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|_| ShareError::Base64)?;
+
+    let (size, compressed) = match smol.split_first_chunk::<SIZE_PREFIX>() {
+        Some((size, compressed)) => (u32::from_le_bytes(*size) as usize, compressed),
+        None => return Err(ShareError::Truncated),
+    };
+
+    if size > limits.decompressed {
+        return Err(ShareError::TooBig { size, limit: limits.decompressed });
+    }
+
+    let mut big = vec![0; size];
+    match lz4_flex::block::decompress_into(compressed, &mut big) {
+        Ok(len) if len == size => (),
+        _ => return Err(ShareError::Lz4),
+    }
+
+    String::from_utf8(big).map_err(|_| ShareError::Utf8)
+}
+
+#[cfg(feature = "share")]
+/// the binary encoding, see the module's docs
+pub fn encode(document: &Document) -> String {
+    let mut w = Writer(vec![VERSION]);
+    w.document(document);
+
+    let crc = crc16(&w.0);
+    w.0.extend(crc.to_le_bytes());
+
+    let mut s = String::from(PREFIX);
+    base64::encode_config_buf(&w.0, base64::URL_SAFE_NO_PAD, &mut s);
+    s
+}
+
+#[cfg(feature = "share")]
+/// either kind of link, checking the limits on ingredients and mixes before building the
+/// document
+pub fn decode(s: &str, limits: &Limits) -> Result<Document> {
+    let document = match s.strip_prefix(PREFIX) {
+        Some(s) => unpack(s, limits)?,
+        None => decode_json(s, limits)?,
+    };
+
+    document.build()?;
+
+    Ok(document)
+}
+
+#[cfg(all(feature = "share", feature = "json"))]
+fn decode_json(s: &str, limits: &Limits) -> Result<Document> {
+    let document = format::parse(&expand(s, limits)?)?;
+
+    let count = document.ingredients.len();
+    if count > limits.ingredients {
+        return Err(ShareError::TooManyIngredients { count, limit: limits.ingredients }.into());
+    }
+
+    let count = document.mixes.len();
+    if count > limits.mixes {
+        return Err(ShareError::TooManyMixes { count, limit: limits.mixes }.into());
+    }
+
+    /* `decode` builds it */
+    format::check_finite(&document)?;
+
+    Ok(document)
+}
+
+#[cfg(all(feature = "share", not(feature = "json")))]
+fn decode_json(_: &str, _: &Limits) -> Result<Document> {
+    Err(ShareError::Json.into())
+}
+
+#[cfg(feature = "share")]
+fn unpack(s: &str, limits: &Limits) -> Result<Document, ShareError> {
+    if s.len() > limits.encoded {
+        return Err(ShareError::TooLong { len: s.len(), limit: limits.encoded });
+    }
+
+    let bytes =
+        base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|_| ShareError::Base64)?;
+
+    let (body, crc) = match bytes.split_last_chunk::<2>() {
+        Some((body, crc)) if !body.is_empty() => (body, u16::from_le_bytes(*crc)),
+        _ => return Err(ShareError::Truncated),
+    };
+
+    if crc16(body) != crc {
+        return Err(ShareError::Checksum);
+    }
+
+    let mut r = Reader { bytes: body, limits };
+
+    match r.byte()? {
+        VERSION => (),
+        v => return Err(ShareError::UnknownVersion(v)),
+    }
+
+    let document = r.document()?;
+
+    if !r.bytes.is_empty() {
+        return Err(ShareError::Malformed("bytes after the document"));
+    }
+
+    Ok(document)
+}
+
+#[cfg(feature = "share")]
+/// CRC-16/CCITT-FALSE
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+    }
+    crc
+}
+
+/// the bits in the byte before an `Inputs`
+const WEIGHT: u8 = 1 << 0;
+const BAKERS: u8 = 1 << 1;
+const IN_OTHER: u8 = 1 << 2;
+const FINAL_WEIGHT: u8 = 1 << 3;
+const IS_FLOUR: u8 = 1 << 4;
+
+/// `d` for a number that's its bits
+const RAW: u64 = 7;
+
+const POW10: [f64; 7] = [1.0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6];
+
+/// `m / 10^d`, the same arithmetic for writing and reading so a number only gets written as a
+/// decimal if it reads back exactly
+fn decimal(m: i64, d: usize) -> Value {
+    (m as f64 / POW10[d]) as Value
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+/// the binary encoding, also for `message`
+pub(crate) struct Writer(pub(crate) Vec<u8>);
+
+impl Writer {
+    pub(crate) fn varint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.0.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.0.push(n as u8);
+    }
+
+    pub(crate) fn string(&mut self, s: &str) {
+        self.varint(s.len() as u64);
+        self.0.extend(s.as_bytes());
+    }
+
+    pub(crate) fn option<T>(&mut self, option: &Option<T>, f: impl FnOnce(&mut Self, &T)) {
+        self.0.push(option.is_some() as u8);
+        if let Some(t) = option {
+            f(self, t)
+        }
+    }
+
+    fn number(&mut self, v: Value) {
+        let exact = (0..POW10.len()).find_map(|d| {
+            let x = v as f64 * POW10[d];
+            /* rounding, there's no `f64::round` without std */
+            let m = if x < 0.0 { x - 0.5 } else { x + 0.5 } as i64;
+            let small = m.unsigned_abs() < 1 << 53;
+            (small && decimal(m, d) == v).then_some(zigzag(m) << 3 | d as u64)
+        });
+        self.varint(exact.unwrap_or((v.to_bits() as u64) << 3 | RAW));
+    }
+
+    fn inputs(&mut self, inputs: &Inputs, flags: u8) {
+        let Inputs { weight, bakers, in_other, final_weight } = *inputs;
+        let fields = [
+            (WEIGHT, weight),
+            (BAKERS, bakers),
+            (IN_OTHER, in_other),
+            (FINAL_WEIGHT, final_weight),
+        ];
+
+        let mask = fields
+            .iter()
+            .filter(|(_, v)| v.is_some())
+            .fold(flags, |mask, (bit, _)| mask | bit);
+        self.0.push(mask);
+
+        for v in fields.into_iter().filter_map(|(_, v)| v) {
+            self.number(v);
+        }
+    }
+
+    pub(crate) fn document(&mut self, document: &Document) {
+        let Document { meta, ingredients, total, flour, mixes } = document;
+        let Meta { title, yields, notes, source } = meta;
+
+        self.string(title);
+        self.option(yields, |w, Yield { count, unit }| {
+            w.varint(*count as u64);
+            w.string(unit);
+        });
+        self.string(notes);
+        self.option(source, |w, source| w.string(source));
+
+        self.varint(ingredients.len() as u64);
+        for Ingredient { id, name, is_flour, inputs } in ingredients {
+            self.varint(*id as u64);
+            self.string(name);
+            self.inputs(inputs, if *is_flour { IS_FLOUR } else { 0 });
+        }
+
+        self.inputs(total, 0);
+        self.inputs(flour, 0);
+
+        self.varint(mixes.len() as u64);
+        for Mix { name, total, flour, ingredients } in mixes {
+            self.string(name);
+            self.inputs(total, 0);
+            self.inputs(flour, 0);
+            self.varint(ingredients.len() as u64);
+            for MixIngredient { id, inputs } in ingredients {
+                self.varint(*id as u64);
+                self.inputs(inputs, 0);
+            }
+        }
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) limits: &'a Limits,
+}
+
+impl Reader<'_> {
+    pub(crate) fn byte(&mut self) -> Result<u8, ShareError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(ShareError::Truncated)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, ShareError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Ok(n);
+            }
+        }
+        Err(ShareError::Malformed("varint too long"))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, ShareError> {
+        u32::try_from(self.varint()?).map_err(|_| ShareError::Malformed("number too big"))
+    }
+
+    /// a count of things that each take at least a byte, so never more than what's left
+    pub(crate) fn count(&mut self) -> Result<usize, ShareError> {
+        match self.varint()? {
+            n if n > self.bytes.len() as u64 => Err(ShareError::Truncated),
+            n => Ok(n as usize),
+        }
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, ShareError> {
+        let len = self.count()?;
+        let (s, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        String::from_utf8(s.to_vec()).map_err(|_| ShareError::Utf8)
+    }
+
+    pub(crate) fn option<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, ShareError>,
+    ) -> Result<Option<T>, ShareError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => f(self).map(Some),
+            _ => Err(ShareError::Malformed("option isn't 0 or 1")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, ShareError> {
+        let n = self.varint()?;
+        let v = match n & 7 {
+            RAW => {
+                let bits =
+                    u32::try_from(n >> 3).map_err(|_| ShareError::Malformed("number too big"))?;
+                Value::from_bits(bits)
+            }
+            d => decimal(unzigzag(n >> 3), d as usize),
+        };
+        match v.is_finite() {
+            true => Ok(v),
+            false => Err(ShareError::Malformed("number isn't finite")),
+        }
+    }
+
+    /// the inputs and the flags that were with them
+    fn inputs(&mut self, flags: u8) -> Result<(Inputs, u8), ShareError> {
+        let mask = self.byte()?;
+        if mask & !(WEIGHT | BAKERS | IN_OTHER | FINAL_WEIGHT | flags) != 0 {
+            return Err(ShareError::Malformed("unknown inputs bits"));
+        }
+
+        let mut field = |bit| match mask & bit {
+            0 => Ok(None),
+            _ => self.number().map(Some),
+        };
+
+        let inputs = Inputs {
+            weight: field(WEIGHT)?,
+            bakers: field(BAKERS)?,
+            in_other: field(IN_OTHER)?,
+            final_weight: field(FINAL_WEIGHT)?,
+        };
+
+        Ok((inputs, mask & flags))
+    }
+
+    fn ingredients(&mut self) -> Result<usize, ShareError> {
+        let count = self.count()?;
+        match count > self.limits.ingredients {
+            true => Err(ShareError::TooManyIngredients { count, limit: self.limits.ingredients }),
+            false => Ok(count),
+        }
+    }
+
+    pub(crate) fn document(&mut self) -> Result<Document, ShareError> {
+        let meta = Meta {
+            title: self.string()?,
+            yields: self.option(|r| Ok(Yield { count: r.u32()?, unit: r.string()? }))?,
+            notes: self.string()?,
+            source: self.option(Self::string)?,
+        };
+
+        let count = self.ingredients()?;
+        let mut ingredients = Vec::with_capacity(count);
+        for _ in 0..count {
+            let id = self.u32()?;
+            let name = self.string()?;
+            let (inputs, flags) = self.inputs(IS_FLOUR)?;
+            ingredients.push(Ingredient { id, name, is_flour: flags != 0, inputs });
+        }
+
+        let (total, _) = self.inputs(0)?;
+        let (flour, _) = self.inputs(0)?;
+
+        let count = self.count()?;
+        if count > self.limits.mixes {
+            return Err(ShareError::TooManyMixes { count, limit: self.limits.mixes });
+        }
+
+        let mut mixes = Vec::with_capacity(count);
+        for _ in 0..count {
+            let name = self.string()?;
+            let (total, _) = self.inputs(0)?;
+            let (flour, _) = self.inputs(0)?;
+
+            let count = self.ingredients()?;
+            let mut ingredients = Vec::with_capacity(count);
+            for _ in 0..count {
+                let id = self.u32()?;
+                let (inputs, _) = self.inputs(0)?;
+                ingredients.push(MixIngredient { id, inputs });
+            }
+
+            mixes.push(Mix { name, total, flour, ingredients });
+        }
+
+        Ok(Document { meta, ingredients, total, flour, mixes })
+    }
+}
+
+#[cfg(all(test, feature = "share"))]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const PIZZA: &str = include_str!("../corpus/v1_pizza.json");
+
+    /* links from before documents paired with their payloads, see `format` */
+    const LINKS: &[(&str, &str)] = &[
+        (
+            include_str!("../corpus/v0_pizza.link"),
+            include_str!("../corpus/v0_pizza.json"),
+        ),
+        (
+            include_str!("../corpus/v0_baguette.link"),
+            include_str!("../corpus/v0_baguette.json"),
+        ),
+        (
+            include_str!("../corpus/v1_pizza.link"),
+            include_str!("../corpus/v1_pizza.json"),
+        ),
+        (
+            include_str!("../corpus/v1_rye.link"),
+            include_str!("../corpus/v1_rye.json"),
+        ),
+    ];
+
+    /// xorshift, so the "fuzzing" is the same every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn link(bytes: &[u8]) -> String {
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    #[test]
+    fn test_expand() {
+        let limits = Limits::default();
+
+        assert_eq!(expand(&compact(PIZZA), &limits).as_deref(), Ok(PIZZA));
+        assert_eq!(expand(&compact(""), &limits).as_deref(), Ok(""));
+
+        for (link, json) in LINKS {
+            assert_eq!(expand(link.trim(), &limits).as_deref(), Ok(json.trim()));
+        }
+
+        assert_eq!(expand("", &limits), Err(ShareError::Truncated));
+        assert_eq!(expand("not base64!", &limits), Err(ShareError::Base64));
+        assert_eq!(
+            expand(&"A".repeat(limits.encoded + 1), &limits),
+            Err(ShareError::TooLong { len: limits.encoded + 1, limit: limits.encoded })
+        );
+
+        /* a few bytes claiming to be 4GiB */
+        assert_eq!(
+            expand(&link(&[0xff, 0xff, 0xff, 0xff, 0x10, b'x']), &limits),
+            Err(ShareError::TooBig { size: u32::MAX as usize, limit: limits.decompressed })
+        );
+
+        /* claiming less or more than is there */
+        let mut smol = lz4_flex::block::compress_prepend_size(PIZZA.as_bytes());
+        smol[0] = smol[0].wrapping_sub(1);
+        assert_eq!(expand(&link(&smol), &limits), Err(ShareError::Lz4));
+        smol[0] = smol[0].wrapping_add(2);
+        assert_eq!(expand(&link(&smol), &limits), Err(ShareError::Lz4));
+
+        let not_text = lz4_flex::block::compress_prepend_size(&[0xff, 0xfe]);
+        assert_eq!(expand(&link(&not_text), &limits), Err(ShareError::Utf8));
+    }
+
+    #[test]
+    fn test_expand_fuzz() {
+        let limits = Limits { decompressed: 4 * 1024, ..Limits::default() };
+        let good = lz4_flex::block::compress_prepend_size(PIZZA.as_bytes());
+        let mut rng = Rng(0x5eed);
+
+        for _ in 0..20_000 {
+            let mut bytes: Vec<u8> = match rng.below(3) {
+                /* garbage */
+                0 => (0..rng.below(64)).map(|_| rng.next() as u8).collect(),
+                /* cut short */
+                1 => good[..rng.below(good.len())].to_vec(),
+                /* a few bytes changed */
+                _ => good.clone(),
+            };
+
+            for _ in 0..rng.below(4) {
+                if !bytes.is_empty() {
+                    let at = rng.below(bytes.len());
+                    bytes[at] = rng.next() as u8;
+                }
+            }
+
+            if let Ok(s) = expand(&link(&bytes), &limits) {
+                assert!(s.len() <= limits.decompressed);
+            }
+        }
+
+        /* and links that aren't base64 of anything */
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_=+/ ~";
+        for _ in 0..5_000 {
+            let s: String = (0..rng.below(32))
+                .map(|_| alphabet[rng.below(alphabet.len())] as char)
+                .collect();
+            let _ = expand(&s, &limits);
+        }
+    }
+
+    fn bread() -> Document {
+        let (wheat, water, salt) = (0, 7, 300);
+
+        Document {
+            meta: Meta {
+                title: "bread 🍞".into(),
+                yields: Some(Yield { count: 2, unit: "loaves".into() }),
+                source: Some("froghat.ca".into()),
+                ..Default::default()
+            },
+            ingredients: vec![
+                Ingredient::new(wheat, "wheat", true),
+                Ingredient {
+                    inputs: Inputs {
+                        bakers: Some(0.72),
+                        final_weight: Some(12.5),
+                        ..Default::default()
+                    },
+                    ..Ingredient::new(water, "water", false)
+                },
+                Ingredient {
+                    inputs: Inputs::bakers(1.0 / 3.0),
+                    ..Ingredient::new(salt, "salt", false)
+                },
+            ],
+            flour: Inputs::weight(500.0),
+            mixes: vec![Mix {
+                name: "poolish".into(),
+                ingredients: vec![
+                    MixIngredient { id: wheat, inputs: Inputs::in_other(0.2) },
+                    MixIngredient { id: water, inputs: Inputs::bakers(1.0) },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// a binary link of `body` with a checksum that matches
+    fn pack(body: &[u8]) -> String {
+        let mut bytes = body.to_vec();
+        bytes.extend(crc16(body).to_le_bytes());
+        format!("{PREFIX}{}", link(&bytes))
+    }
+
+    fn unpacked(s: &str) -> Vec<u8> {
+        let mut bytes = base64::decode_config(&s[1..], base64::URL_SAFE_NO_PAD).unwrap();
+        bytes.truncate(bytes.len() - 2);
+        bytes
+    }
+
+    #[test]
+    fn test_pack() {
+        let limits = Limits::default();
+        let bread = bread();
+
+        let s = encode(&bread);
+        assert!(s.starts_with(PREFIX));
+        assert_eq!(decode(&s, &limits), Ok(bread.clone()));
+
+        let body = unpacked(&s);
+        assert_eq!(body[0], VERSION);
+
+        /* numbers read back exactly, short decimals in a byte or two */
+        for v in [
+            0.0,
+            0.72,
+            500.0,
+            -2.5,
+            1.0 / 3.0,
+            1e30,
+            Value::MIN_POSITIVE,
+            123456.7,
+        ] {
+            let mut w = Writer(Vec::new());
+            w.number(v);
+            let mut r = Reader { bytes: &w.0, limits: &limits };
+            assert_eq!(r.number().unwrap().to_bits(), v.to_bits(), "{v}");
+            assert!(r.bytes.is_empty());
+            if [0.72, 500.0, -2.5].contains(&v) {
+                assert!(w.0.len() <= 2, "{v}");
+            }
+        }
+
+        let err = |e| Err(Error::Share(e));
+
+        let mut bytes = base64::decode_config(&s[1..], base64::URL_SAFE_NO_PAD).unwrap();
+        bytes[5] ^= 0x10;
+        assert_eq!(
+            decode(&format!("{PREFIX}{}", link(&bytes)), &limits),
+            err(ShareError::Checksum)
+        );
+
+        let mut newer = body.clone();
+        newer[0] = VERSION + 1;
+        assert_eq!(
+            decode(&pack(&newer), &limits),
+            err(ShareError::UnknownVersion(VERSION + 1))
+        );
+
+        assert_eq!(
+            decode(&pack(&body[..body.len() - 1]), &limits),
+            err(ShareError::Truncated)
+        );
+        assert_eq!(
+            decode(&pack(&[body.as_slice(), &[0]].concat()), &limits),
+            err(ShareError::Malformed("bytes after the document"))
+        );
+        assert_eq!(decode(&pack(&[]), &limits), err(ShareError::Truncated));
+        assert_eq!(decode("~", &limits), err(ShareError::Truncated));
+        assert_eq!(decode("~!", &limits), err(ShareError::Base64));
+
+        let limits = Limits { ingredients: 2, ..Limits::default() };
+        assert_eq!(
+            decode(&s, &limits),
+            err(ShareError::TooManyIngredients { count: 3, limit: 2 })
+        );
+
+        let limits = Limits { mixes: 0, ..Limits::default() };
+        assert_eq!(
+            decode(&s, &limits),
+            err(ShareError::TooManyMixes { count: 1, limit: 0 })
+        );
+
+        /* it's checked like any other document */
+        let mut bad = bread.clone();
+        bad.mixes[0].ingredients[0].id = 9;
+        assert!(matches!(
+            decode(&encode(&bad), &Limits::default()),
+            Err(Error::Invalid(_))
+        ));
+
+        #[cfg(not(feature = "json"))]
+        assert_eq!(
+            decode(
+                include_str!("../corpus/v1_pizza.link").trim(),
+                &Limits::default()
+            ),
+            err(ShareError::Json)
+        );
+    }
+
+    #[test]
+    fn test_unpack_fuzz() {
+        let limits = Limits::default();
+        let good = unpacked(&encode(&bread()));
+        let mut rng = Rng(0xb4ead);
+
+        for _ in 0..20_000 {
+            let mut bytes: Vec<u8> = match rng.below(3) {
+                0 => (0..rng.below(64)).map(|_| rng.next() as u8).collect(),
+                1 => good[..rng.below(good.len())].to_vec(),
+                _ => good.clone(),
+            };
+
+            for _ in 0..rng.below(4) {
+                if !bytes.is_empty() {
+                    let at = rng.below(bytes.len());
+                    bytes[at] = rng.next() as u8;
+                }
+            }
+
+            /* with a good checksum so the rest of the decoding gets a go at it */
+            let _ = decode(&pack(&bytes), &limits);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_decode() {
+        let limits = Limits::default();
+
+        /* a V1 link to the web app's default recipe */
+        let old = include_str!("../corpus/v1_pizza.link").trim();
+        let pizza = format::from_str(PIZZA).unwrap();
+        assert_eq!(decode(old, &limits), Ok(pizza.clone()));
+
+        for (link, json) in LINKS {
+            assert_eq!(decode(link.trim(), &limits), format::from_str(json));
+        }
+
+        let new = encode(&pizza);
+        assert_eq!(decode(&new, &limits), Ok(pizza.clone()));
+        assert!(new.len() < compact(&format::to_string(&pizza)).len() / 2);
+
+        let limits = Limits { ingredients: 5, ..Limits::default() };
+        assert_eq!(
+            decode(old, &limits),
+            Err(Error::Share(ShareError::TooManyIngredients {
+                count: 6,
+                limit: 5
+            }))
+        );
+
+        let limits = Limits { mixes: 0, ..Limits::default() };
+        assert_eq!(
+            decode(old, &limits),
+            Err(Error::Share(ShareError::TooManyMixes {
+                count: 1,
+                limit: 0
+            }))
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Solving a recipe for every combination of values of a few inputs, like hydrations by dough
+//! weights for comparing test bakes.
+
+use super::rules::Recipe;
+use super::solve::Solver;
+use super::{Error, Index, Result, Value, Values};
+
+use alloc::vec::Vec;
+
+/// the most variants a sweep can have
+pub const MAX_VARIANTS: usize = 1 << 16;
+
+/// the most values a `Table` can have, 16MiB of `f32`s
+pub const MAX_CELLS: usize = 1 << 22;
+
+/// An input and the values to try for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    pub index: Index,
+    pub values: Vec<Value>,
+}
+
+impl Axis {
+    pub fn new(index: impl Into<Index>, values: Vec<Value>) -> Self {
+        Axis { index: index.into(), values }
+    }
+
+    /// from `start` to `stop` inclusive, every `step`
+    ///
+    /// empty if `stop` is before `start`, fails if `step` isn't positive, if anything isn't
+    /// finite, or with `Error::Capacity` if there'd be more than `MAX_VARIANTS` values
+    pub fn range(index: impl Into<Index>, start: Value, stop: Value, step: Value) -> Result<Self> {
+        if !(start.is_finite() && stop.is_finite() && step.is_finite()) {
+            return Err(Error::Argument("range isn't finite"));
+        }
+        if step <= 0.0 {
+            return Err(Error::Argument("range step isn't positive"));
+        }
+
+        let values = if stop >= start {
+            /* a bit of slack so 0.65 to 0.80 by 0.025 includes 0.80 despite rounding, in f64 so
+             * a tiny step doesn't overflow to infinity, the cast truncates which is the floor
+             * since this isn't negative */
+            let steps = (stop - start) as f64 / step as f64 + 1e-3;
+            if steps >= MAX_VARIANTS as f64 {
+                return Err(Error::Capacity { limit: MAX_VARIANTS });
+            }
+            (0..=steps as usize)
+                .map(|n| start + n as Value * step)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(Axis { index: index.into(), values })
+    }
+}
+
+/// how many variants sweeping `axes` has, fails with `Error::Capacity` if it's more than
+/// `MAX_VARIANTS`
+pub fn variants(axes: &[Axis]) -> Result<usize> {
+    axes.iter()
+        .try_fold(1usize, |n, axis| n.checked_mul(axis.values.len()))
+        .filter(|&n| n <= MAX_VARIANTS)
+        .ok_or(Error::Capacity { limit: MAX_VARIANTS })
+}
+
+/// One row per variant, each row has the axis values followed by the output values.
+///
+/// Variants are ordered like nested loops, the last axis changes fastest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub axes: Vec<Index>,
+    pub outputs: Vec<Index>,
+    pub cells: Vec<Value>,
+}
+
+impl Table {
+    pub fn width(&self) -> usize {
+        self.axes.len() + self.outputs.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len() / self.width().max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn row(&self, variant: usize) -> Option<&[Value]> {
+        let width = self.width();
+        self.cells.get(variant * width..(variant + 1) * width)
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Value]> + '_ {
+        self.cells.chunks(self.width().max(1))
+    }
+}
+
+/// Solves a copy of `values` for each variant, at most `MAX_VARIANTS` of them and `MAX_CELLS`
+/// values in the table.
+///
+/// Only the inputs in `values` are kept, anything it solved is solved again for each variant.
+/// Outputs that can't be solved for a variant are left unsolved in the table.
+///
+/// Without any axes or outputs, there wouldn't be anything in the rows; that's an
+/// `Error::Argument`.
+pub fn sweep(recipe: &Recipe, values: &Values, axes: &[Axis], outputs: &[Index]) -> Result<Table> {
+    if axes.is_empty() && outputs.is_empty() {
+        return Err(Error::Argument("nothing to sweep or output"));
+    }
+
+    let variants = variants(axes)?;
+    let cells = variants
+        .checked_mul(axes.len() + outputs.len())
+        .filter(|&cells| cells <= MAX_CELLS)
+        .ok_or(Error::Capacity { limit: MAX_CELLS })?;
+
+    let mut table = Table {
+        axes: axes.iter().map(|axis| axis.index).collect(),
+        outputs: outputs.to_vec(),
+        cells: Vec::with_capacity(cells),
+    };
+
+    let mut variant_values = values.clone();
+
+    for variant in 0..variants {
+        variant_values.clone_from(values);
+        variant_values.reset();
+
+        /* the variant number in mixed radix, one digit per axis */
+        let mut rest = variant;
+        let start = table.cells.len();
+
+        for axis in axes.iter().rev() {
+            let value = axis.values[rest % axis.values.len()];
+            rest /= axis.values.len();
+            *variant_values.value_mut(axis.index)? = value;
+            table.cells.push(value);
+        }
+
+        table.cells[start..].reverse();
+
+        Solver::new(recipe, &variant_values).solve(&mut variant_values)?;
+
+        table
+            .cells
+            .extend(outputs.iter().map(|&i| variant_values.value(i)));
+    }
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_axis_range() {
+        let range = |start, stop, step| Axis::range(0 as Index, start, stop, step);
+
+        assert_eq!(range(0.65, 0.80, 0.025).unwrap().values.len(), 7);
+        assert_eq!(range(1.0, 1.0, 0.5).unwrap().values, vec![1.0]);
+        assert!(range(1.0, 0.0, 0.5).unwrap().values.is_empty());
+
+        for (start, stop, step) in [
+            (0.0, 1.0, 0.0),
+            (0.0, 1.0, -0.5),
+            (0.0, f32::INFINITY, 1.0),
+            (f32::NAN, 1.0, 0.5),
+        ] {
+            assert!(matches!(range(start, stop, step), Err(Error::Argument(_))));
+        }
+
+        let too_many = Err(Error::Capacity { limit: MAX_VARIANTS });
+        assert_eq!(range(0.0, 1.0, f32::MIN_POSITIVE), too_many);
+        assert_eq!(range(0.0, MAX_VARIANTS as Value, 1.0), too_many);
+        assert!(range(1.0, MAX_VARIANTS as Value, 1.0).is_ok());
+
+        let axis = Axis::new(0 as Index, vec![0.0; 1 << 10]);
+        let capacity = Error::Capacity { limit: MAX_VARIANTS };
+        assert_eq!(variants(&[axis.clone(), axis.clone()]), Err(capacity));
+        assert_eq!(variants(&[axis, Axis::new(1 as Index, vec![])]), Ok(0));
+    }
+
+    #[test]
+    fn test_sweep_hydration_by_weight() {
+        let (recipe, values, wheat, water) = crate::wheat_and_water();
+
+        let total = recipe.dough.total.weight;
+
+        let table = sweep(
+            &recipe,
+            &values,
+            &[
+                Axis::range(water.bakers, 0.65, 0.80, 0.025).unwrap(),
+                Axis::new(total, vec![500.0, 1000.0, 1500.0]),
+            ],
+            &[wheat.weight.index(), water.weight.index()],
+        )
+        .unwrap();
+
+        assert_eq!(table.width(), 4);
+        assert_eq!(table.len(), 7 * 3);
+
+        let row = table.row(1).unwrap();
+        assert_eq!(row[..2], [0.65, 1000.0]);
+        assert_eq!(row[2].round(), 606.0);
+        assert_eq!(row[3].round(), 394.0);
+
+        let row = table.row(20).unwrap();
+        assert_eq!(row[1], 1500.0);
+        assert_eq!(row[2].round(), 833.0);
+        assert_eq!(row[3].round(), 667.0);
+
+        for row in table.rows() {
+            assert!((row[1] - row[2] - row[3]).abs() < 1e-3);
+        }
+
+        let axis = Axis::new(total, vec![1000.0; MAX_VARIANTS]);
+        assert_eq!(
+            sweep(&recipe, &values, &[axis], &[total.index(); 64]),
+            Err(Error::Capacity { limit: MAX_CELLS })
+        );
+
+        /* rows of nothing, there'd be no telling how many */
+        assert!(matches!(
+            sweep(&recipe, &values, &[], &[]),
+            Err(Error::Argument(_))
+        ));
+
+        /* one variant, just the outputs */
+        let table = sweep(&recipe, &values, &[], &[total.index()]).unwrap();
+        assert_eq!(table.len(), 1);
+    }
+}
@@ -0,0 +1,158 @@
+//! Checking that a recipe has the shape `rules` expects, since `rules` skips what it can't
+//! relate without complaint.
+
+use super::path::{MixPath, Slot};
+use super::rules::{Item, Recipe};
+use super::{Error, Result};
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::once;
+
+/// Something wrong with the item at `mix` and `slot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Problem {
+    pub mix: MixPath,
+    pub slot: Slot,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// the dough's items should all be `WithMixes`
+    InMixInDough,
+    /// a mix's items should all be `InMix`
+    WithMixesInMix,
+    /// an ingredient in a mix without an ingredient with the same id in the same list in the
+    /// dough
+    NoMatchingTotal,
+    /// an ingredient with the same id as one before it in the same list
+    DuplicateId,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}: {}", self.mix, self.slot, self.kind)
+    }
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let why = match self {
+            ProblemKind::InMixInDough => "mix item in the dough",
+            ProblemKind::WithMixesInMix => "dough item in a mix",
+            ProblemKind::NoMatchingTotal => "no ingredient in the dough with this id",
+            ProblemKind::DuplicateId => "same id as an earlier ingredient",
+        };
+        f.write_str(why)
+    }
+}
+
+impl Recipe {
+    /// `Error::Invalid` with every problem found by `problems`, if there are any
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Invalid(problems))
+        }
+    }
+
+    /// structural problems in the dough then in each mix, in the order of `Mix::slots`
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        let mixes = self.mixes.iter().enumerate();
+
+        for (mix_path, mix) in
+            once((MixPath::Dough, &self.dough)).chain(mixes.map(|(i, mix)| (MixPath::Mix(i), mix)))
+        {
+            let mut seen = Vec::new();
+
+            for (slot, item) in mix.slots() {
+                let mut problem = |kind| problems.push(Problem { mix: mix_path, slot, kind });
+
+                if seen.contains(&slot) {
+                    problem(ProblemKind::DuplicateId);
+                }
+                seen.push(slot);
+
+                match (mix_path, item) {
+                    (MixPath::Dough, Item::InMix(_)) => problem(ProblemKind::InMixInDough),
+                    (MixPath::Mix(_), Item::WithMixes(_)) => problem(ProblemKind::WithMixesInMix),
+                    _ => (),
+                }
+
+                if let (MixPath::Mix(_), Slot::Flours(_) | Slot::NonFlours(_)) = (mix_path, slot) {
+                    if self.dough.item(slot).is_none() {
+                        problem(ProblemKind::NoMatchingTotal);
+                    }
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solve::Solver;
+    use crate::Ingredient;
+
+    #[test]
+    fn test_validate() {
+        let (mut recipe, mut values, _, _) = crate::wheat_and_water();
+
+        let mut levain = values.minimal_mix().unwrap();
+        levain
+            .flours
+            .push(Ingredient::new(0, values.new_mix_item().unwrap()));
+        levain
+            .nonflours
+            .push(Ingredient::new(1, values.new_mix_item().unwrap()));
+        recipe.mixes.push(levain);
+
+        assert_eq!(recipe.validate(), Ok(()));
+        assert!(Solver::validated(&recipe, &values).is_ok());
+
+        /* nonflour 1 is only in the dough's nonflours */
+        let levain = &mut recipe.mixes[0];
+        levain
+            .flours
+            .push(Ingredient::new(1, values.new_mix_item().unwrap()));
+        levain
+            .flours
+            .push(Ingredient::new(0, values.new_mix_item().unwrap()));
+        levain.nonflour = values.new_item().unwrap().into();
+        recipe.dough.total = values.new_mix_item().unwrap().into();
+
+        let problems = recipe.problems();
+        let at = |mix, slot, kind| Problem { mix, slot, kind };
+        assert_eq!(
+            problems,
+            [
+                at(MixPath::Dough, Slot::Total, ProblemKind::InMixInDough),
+                at(MixPath::Mix(0), Slot::NonFlour, ProblemKind::WithMixesInMix),
+                at(
+                    MixPath::Mix(0),
+                    Slot::Flours(1),
+                    ProblemKind::NoMatchingTotal
+                ),
+                at(MixPath::Mix(0), Slot::Flours(0), ProblemKind::DuplicateId),
+            ]
+        );
+        assert_eq!(
+            problems[2].to_string(),
+            "mixes[0].flours[1]: no ingredient in the dough with this id"
+        );
+
+        assert_eq!(recipe.validate(), Err(Error::Invalid(problems.clone())));
+        assert_eq!(
+            Solver::validated(&recipe, &values).err(),
+            Some(Error::Invalid(problems))
+        );
+    }
+}
@@ -117,8 +117,13 @@ export const Bread = (props: BreadProps) => {
 
     if (!(hash = props.history().url.hash.slice(1))) return;
 
-    if (   !(string = wasm.base64_expand(hash))
-        || !(recipe = deserializeFromString(string)))
+    try {
+      string = wasm.base64_expand(hash);
+    } catch (error) {
+      return console.error("failed to load state from hash", { hash, error });
+    }
+
+    if (!(recipe = deserializeFromString(string)))
       return console.error("failed to load state from hash", { hash, string });
 
     /* maybe send this this through update() instead? */
@@ -131,90 +136,83 @@ export const Bread = (props: BreadProps) => {
   function solveRecipe(recipe: Recipe, update: (..._: any[]) => void) {
     type Path = (keyof Recipe | keyof Mix | keyof Amounts | number)
 
-    let path, solutions;
     const paths = new Map();
-    const writer = new wasm.RecipeWriter(4096)
-
-    writeRecipe(recipe);
+    const writer = new wasm.RecipeWriter()
 
-    if (!(solutions = writer.solve()))
-      return console.error("failed solve");
+    try {
+      writeRecipe(recipe);
+      writer.solve_in_place();
+    } catch (e) {
+      return console.error("failed solve", e);
+    }
 
     const newrecipe = deepcopy(recipe);
 
+    const states = writer.states();
+    const values = writer.values();
+
+    /* derived, default or conflicting, see State::code in overproofed */
+    const isSolved = (code: number) => code === 2 || code === 3 || code === 5;
+
     /* setrattr and reconcile turns out to be much faster than the store
     * setter, even in batch */
-    solutions.forEach((v, i) => (path = paths.get(i)) && setrattr(newrecipe, path, v))
+    paths.forEach((path, i) => isSolved(states[i]) && setrattr(newrecipe, path, values[i]))
 
     update(reconcile(newrecipe))
 
     return;
 
     function writeRecipe(recipe: Recipe) {
-      let path: Path[], mix: Mix;
+      const mixes = [recipe.total, ...recipe.mixes];
+      const nitems = recipe.items.length;
 
-      path = ['total'];
-      mix = recipe.total;
+      /* see layout in overproofed-wasm */
+      const flags = new Uint8Array(recipe.items.map(({ is_flour }) => is_flour ? 0 : 1));
+      const membership = new Uint8Array(Math.ceil(mixes.length * nitems / 8));
+      const inputs: number[] = [];
+      /* the path of each input, null where the web app has no such thing */
+      const inputPaths: (Path[] | null)[] = [];
 
-      writer.total();
-      _writeAmounts(mix.total, ...path, 'total');
-
-      writer.flour();
-      _writeAmounts(mix.flour, ...path, 'flour');
-
-      mix.amounts.forEach((amounts, i) => {
-        const { is_flour } = recipe.items[i];
-
-        const flags = new wasm.NewItemFlags();
-        is_flour ? flags.flour() : flags.nonflour();
-        amounts ? flags.total_item() : flags.hole();
-        writer.new_item(flags);
-        amounts && _writeAmounts(amounts, ...path, 'amounts', i)
-
-        writeValue(writer.weight_less_mixes(), recipe.final[i], 'final', i)
-      })
+      mixes.forEach((mix, m) => {
+        const path: Path[] = m === 0 ? ['total'] : ['mixes', m - 1];
 
-      recipe.mixes
-        .forEach((mix, i) => {
-          path = ['mixes', i];
+        writeAmounts(mix.total, null, null, ...path, 'total');
+        writeAmounts(mix.flour, null, null, ...path, 'flour');
+        writeNothing();
 
-          writer.new_mix();
+        mix.amounts.forEach((amounts, i) => {
+          if (!amounts) return;
 
-          writer.total();
-          _writeMixAmounts(mix.total, ...path, 'total');
+          const bit = m * nitems + i;
+          membership[bit >> 3] |= 1 << (bit & 7);
 
-          writer.flour();
-          _writeMixAmounts(mix.flour, ...path, 'flour');
-
-          mix.amounts.forEach((amounts, i) => {
-            const { is_flour } = recipe.items[i];
-
-            const flags = new wasm.NewItemFlags();
-            is_flour ? flags.flour() : flags.nonflour();
-            amounts ? flags.mix_item() : flags.hole();
-            writer.new_item(flags);
-
-            amounts && _writeMixAmounts(amounts, ...path, 'amounts', i)
-          })
+          if (m === 0)
+            writeAmounts(amounts, recipe.final[i], ['final', i], ...path, 'amounts', i);
+          else
+            writeAmounts(amounts, null, null, ...path, 'amounts', i);
         })
-    }
-
-    function _writeAmounts(amounts: Amounts, ...path: Path[]) {
-      writeValue(writer.weight(), amounts.weight, ...path, 'weight')
-      writeValue(writer.bakers(), amounts.bakers, ...path, 'bakers')
-      writeValue(writer.percent_in_mixes(), amounts.in_other, ...path, 'in_other')
-    }
+      })
 
-    function _writeMixAmounts(amounts: Amounts, ...path: Path[]) {
-      writeValue(writer.weight(), amounts.weight, ...path, 'weight')
-      writeValue(writer.bakers(), amounts.bakers, ...path, 'bakers')
-      writeValue(writer.percent_of_total(), amounts.in_other, ...path, 'in_other')
-    }
+      writer
+        .load(mixes.length - 1, flags, membership, new Float32Array(inputs))
+        .forEach((index, n) => index >= 0 && inputPaths[n] && paths.set(index, inputPaths[n]));
+
+      function writeAmounts(amounts: Amounts, final: number | null, finalPath: Path[] | null, ...path: Path[]) {
+        (['weight', 'bakers', 'in_other'] as const).forEach((key) => {
+          inputs.push(amounts[key] ?? NaN);
+          inputPaths.push([...path, key]);
+        });
+        inputs.push(final ?? NaN);
+        inputPaths.push(finalPath);
+      }
 
-    function writeValue(value_index: number, input_value: number | null, ...path: Path[]) {
-      paths.set(value_index, path);
-      if (input_value !== null)
-        writer.set(value_index, input_value)
+      /* for the nonflour, which the web app only ever solves for */
+      function writeNothing() {
+        for (let field = 0; field < 4; field++) {
+          inputs.push(NaN);
+          inputPaths.push(null);
+        }
+      }
     }
 
   }